@@ -1,2 +1 @@
-pub mod timer;
 mod generic_event;
\ No newline at end of file
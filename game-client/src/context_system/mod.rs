@@ -1,5 +1,12 @@
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
 
+pub mod provider;
+
 pub struct ContextSystemPlugin;
 
 impl Plugin for ContextSystemPlugin {
@@ -7,75 +14,333 @@ impl Plugin for ContextSystemPlugin {
     }
 }
 
-pub trait ContextCommands
-{
+// Nothing on the searched entities had the context component. Also triggered as a global event when
+// a context command finds nothing and has no fallback of its own.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContextNotFound {
+    pub entity: Entity,
+    pub component: &'static str,
+    pub include_self: bool,
+}
+
+impl ContextNotFound {
+    fn new<C: Component>(entity: Entity, include_self: bool) -> Self {
+        Self {
+            entity,
+            component: std::any::type_name::<C>(),
+            include_self,
+        }
+    }
+}
+
+impl Display for ContextNotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.include_self {
+            write!(f, "neither {} nor its ancestors have {}", self.entity, self.component)
+        } else {
+            write!(f, "no ancestor of {} has {}", self.entity, self.component)
+        }
+    }
+}
+
+impl std::error::Error for ContextNotFound {}
+
+// Finds the nearest ancestor with context C right away, unlike ContextCommands. The _inclusive variants
+// also check the entity itself first. D is the query data for C, see ContextQueryMut.
+#[derive(SystemParam)]
+pub struct ContextQuery<'w, 's, C: Component, D: QueryData<ReadOnly = &'static C> + 'static = &'static C> {
+    parents: Query<'w, 's, &'static Parent>,
+    contexts: Query<'w, 's, D>,
+    context: PhantomData<C>,
+}
+
+// Write access to the nearest context.
+pub type ContextQueryMut<'w, 's, C> = ContextQuery<'w, 's, C, &'static mut C>;
+
+impl<'w, 's, C: Component, D: QueryData<ReadOnly = &'static C>> ContextQuery<'w, 's, C, D> {
+    pub fn entity(&self, entity: Entity) -> Result<Entity, ContextNotFound> {
+        self.find(entity, false)
+    }
+
+    pub fn entity_inclusive(&self, entity: Entity) -> Result<Entity, ContextNotFound> {
+        self.find(entity, true)
+    }
+
+    pub fn get(&self, entity: Entity) -> Result<(Entity, &C), ContextNotFound> {
+        let context_entity = self.find(entity, false)?;
+        Ok((context_entity, self.contexts.get(context_entity).unwrap()))
+    }
+
+    pub fn get_inclusive(&self, entity: Entity) -> Result<(Entity, &C), ContextNotFound> {
+        let context_entity = self.find(entity, true)?;
+        Ok((context_entity, self.contexts.get(context_entity).unwrap()))
+    }
+
+    fn find(&self, entity: Entity, include_self: bool) -> Result<Entity, ContextNotFound> {
+        find_context(entity, include_self, |entity| self.parents.parent(entity), |entity| self.contexts.contains(entity))
+            .ok_or(ContextNotFound::new::<C>(entity, include_self))
+    }
+}
+
+impl<'w, 's, C: Component> ContextQueryMut<'w, 's, C> {
+    pub fn get_mut(&mut self, entity: Entity) -> Result<(Entity, Mut<'_, C>), ContextNotFound> {
+        let context_entity = self.find(entity, false)?;
+        Ok((context_entity, self.contexts.get_mut(context_entity).unwrap()))
+    }
+
+    pub fn get_mut_inclusive(&mut self, entity: Entity) -> Result<(Entity, Mut<'_, C>), ContextNotFound> {
+        let context_entity = self.find(entity, true)?;
+        Ok((context_entity, self.contexts.get_mut(context_entity).unwrap()))
+    }
+}
+
+// Walks entity (if include_self) and its ancestors, parent_of gives the next one up.
+fn find_context(
+    entity: Entity,
+    include_self: bool,
+    parent_of: impl Fn(Entity) -> Option<Entity>,
+    has_context: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+    let first = if include_self { Some(entity) } else { parent_of(entity) };
+    std::iter::successors(first, |entity| parent_of(*entity)).find(|entity| has_context(*entity))
+}
+
+// The nearest entity with context C, starting at entity itself when include_self is set.
+pub fn find_context_entity<C: Component>(world: &World, entity: Entity, include_self: bool) -> Result<Entity, ContextNotFound> {
+    find_context(
+        entity,
+        include_self,
+        |entity| world.get::<Parent>(entity).map(Parent::get),
+        |entity| world.get::<C>(entity).is_some(),
+    )
+    .ok_or(ContextNotFound::new::<C>(entity, include_self))
+}
+
+// What the context commands do when they find nothing and no fallback was given. Observers of
+// ContextNotFound can pick it up from there.
+pub fn report_context_not_found(world: &mut World, not_found: ContextNotFound) {
+    warn!("Context not found: {}", not_found);
+    world.trigger(not_found);
+}
+
+// Runs closures on the nearest ancestor with context C. World runs them right away, Commands and
+// DeferredWorld when commands apply.
+// modify_context and process_context report a missing context through report_context_not_found, which
+// warns and triggers a global ContextNotFound. The _with variants choose whether entity itself is
+// checked and what happens instead, e.g. a no-op not_found_fn where a missing context is expected.
+pub trait ContextCommands {
+    fn modify_context_with<F, C, NF>(&mut self, entity: Entity, include_self: bool, modifier_fn: F, not_found_fn: NF)
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static;
+
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, entity: Entity, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF)
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static;
 
     fn modify_context<F, C>(&mut self, entity: Entity, modifier_fn: F)
     where
         F: FnOnce(&mut C) + Send + Sync + 'static,
         C: Component
-    ;
+    {
+        self.modify_context_with(entity, false, modifier_fn, report_context_not_found);
+    }
 
     fn process_context<C, FP, FW, R>(&mut self, entity: Entity, process_fn: FP, world_fn: FW)
     where
         FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
         FW: FnOnce(&mut World, R) + Send + Sync + 'static,
-        C: Component;
+        C: Component
+    {
+        self.process_context_with(entity, false, process_fn, world_fn, report_context_not_found);
+    }
+}
+
+impl ContextCommands for World {
+    fn modify_context_with<F, C, NF>(&mut self, entity: Entity, include_self: bool, modifier_fn: F, not_found_fn: NF)
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        self.process_context_with(entity, include_self, |_, context: &mut C| modifier_fn(context), |_, _| {}, not_found_fn);
+    }
 
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, entity: Entity, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF)
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        match find_context_entity::<C>(self, entity, include_self) {
+            Ok(context_entity) => {
+                let mut context = self.get_mut::<C>(context_entity).unwrap();
+                let ret = process_fn(context_entity, &mut *context);
+                world_fn(self, ret);
+            }
+            Err(not_found) => not_found_fn(self, not_found),
+        }
+    }
 }
 
 impl<'w, 's> ContextCommands for Commands<'w, 's> {
-    fn modify_context<F, C>(&mut self, entity: Entity, modifier_fn: F)
+    fn modify_context_with<F, C, NF>(&mut self, entity: Entity, include_self: bool, modifier_fn: F, not_found_fn: NF)
     where
         F: FnOnce(&mut C) + Send + Sync + 'static,
-        C: Component {
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
         self.queue(move |world: &mut World| {
-            let mut next_child = entity;
-            loop {
-                let next_parent = world.get::<Parent>(next_child);
-                match next_parent {
-                    Some(next_parent) => {
-                        let next_parent = next_parent.get();
-                        let nav = world.get_mut::<C>(next_parent.clone());
-                        match nav {
-                            Some(mut nav) => {
-                                modifier_fn(&mut *nav);
-                                return;
-                            }
-                            None => { next_child = next_parent; }
-                        }
-                    }
-                    None => break
-                }
-            }
+            world.modify_context_with(entity, include_self, modifier_fn, not_found_fn);
         });
     }
 
-    fn process_context<C, FP, FW, R>(&mut self, entity: Entity, process_fn: FP, world_fn: FW)
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, entity: Entity, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF)
     where
         FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
         FW: FnOnce(&mut World, R) + Send + Sync + 'static,
-        C: Component {
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
         self.queue(move |world: &mut World| {
-            let mut next_child = entity;
-            loop {
-                let next_parent = world.get::<Parent>(next_child);
-                match next_parent {
-                    Some(next_parent) => {
-                        let next_parent = next_parent.get();
-                        let nav = world.get_mut::<C>(next_parent.clone());
-                        match nav {
-                            Some(mut nav) => {
-                                let ret = process_fn(next_parent, &mut *nav);
-                                world_fn(world, ret);
-                                return;
-                            }
-                            None => { next_child = next_parent; }
-                        }
-                    }
-                    None => break
-                }
-            }
-        })
-        }
+            world.process_context_with(entity, include_self, process_fn, world_fn, not_found_fn);
+        });
+    }
+}
+
+impl<'w> ContextCommands for DeferredWorld<'w> {
+    fn modify_context_with<F, C, NF>(&mut self, entity: Entity, include_self: bool, modifier_fn: F, not_found_fn: NF)
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        self.commands().modify_context_with(entity, include_self, modifier_fn, not_found_fn);
+    }
+
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, entity: Entity, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF)
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        self.commands().process_context_with(entity, include_self, process_fn, world_fn, not_found_fn);
+    }
+}
+
+// ContextCommands starting from the entity of the EntityCommands.
+pub trait EntityContextCommands {
+    fn modify_context_with<F, C, NF>(&mut self, include_self: bool, modifier_fn: F, not_found_fn: NF) -> &mut Self
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static;
+
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF) -> &mut Self
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static;
+
+    fn modify_context<F, C>(&mut self, modifier_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component
+    {
+        self.modify_context_with(false, modifier_fn, report_context_not_found)
+    }
+
+    fn process_context<C, FP, FW, R>(&mut self, process_fn: FP, world_fn: FW) -> &mut Self
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component
+    {
+        self.process_context_with(false, process_fn, world_fn, report_context_not_found)
+    }
+}
+
+impl<'a> EntityContextCommands for EntityCommands<'a> {
+    fn modify_context_with<F, C, NF>(&mut self, include_self: bool, modifier_fn: F, not_found_fn: NF) -> &mut Self
+    where
+        F: FnOnce(&mut C) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        let entity = self.id();
+        self.commands().modify_context_with(entity, include_self, modifier_fn, not_found_fn);
+        self
+    }
+
+    fn process_context_with<C, FP, FW, R, NF>(&mut self, include_self: bool, process_fn: FP, world_fn: FW, not_found_fn: NF) -> &mut Self
+    where
+        FP: FnOnce(Entity, &mut C) -> R + Send + Sync + 'static,
+        FW: FnOnce(&mut World, R) + Send + Sync + 'static,
+        C: Component,
+        NF: FnOnce(&mut World, ContextNotFound) + Send + Sync + 'static {
+        let entity = self.id();
+        self.commands().process_context_with(entity, include_self, process_fn, world_fn, not_found_fn);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use super::*;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Depth(u32);
+
+    // root (Depth 0) > middle > leaf (Depth 2), and an unrelated orphan.
+    fn hierarchy(world: &mut World) -> [Entity; 4] {
+        let root = world.spawn(Depth(0)).id();
+        let middle = world.spawn_empty().set_parent(root).id();
+        let leaf = world.spawn(Depth(2)).set_parent(middle).id();
+        let orphan = world.spawn_empty().id();
+        [root, middle, leaf, orphan]
+    }
+
+    #[test]
+    fn context_query_finds_self_or_ancestor() {
+        let mut world = World::new();
+        let [root, middle, leaf, orphan] = hierarchy(&mut world);
+        world.run_system_once(move |contexts: ContextQuery<Depth>| {
+            assert_eq!(contexts.entity(leaf), Ok(root));
+            assert_eq!(contexts.entity_inclusive(leaf), Ok(leaf));
+            assert_eq!(contexts.get(middle), Ok((root, &Depth(0))));
+            assert_eq!(contexts.get_inclusive(leaf), Ok((leaf, &Depth(2))));
+
+            assert_eq!(contexts.entity(root), Err(ContextNotFound::new::<Depth>(root, false)));
+            assert_eq!(contexts.entity_inclusive(root), Ok(root));
+            assert_eq!(contexts.get_inclusive(orphan), Err(ContextNotFound::new::<Depth>(orphan, true)));
+        }).unwrap();
+    }
+
+    #[test]
+    fn context_query_mut_finds_self_or_ancestor() {
+        let mut world = World::new();
+        let [root, middle, leaf, orphan] = hierarchy(&mut world);
+        world.run_system_once(move |mut contexts: ContextQueryMut<Depth>| {
+            assert_eq!(contexts.entity(leaf), Ok(root));
+            assert_eq!(contexts.get(middle), Ok((root, &Depth(0))));
+
+            contexts.get_mut(leaf).unwrap().1.0 = 10;
+            contexts.get_mut_inclusive(leaf).unwrap().1.0 = 20;
+            assert!(contexts.get_mut(root).is_err());
+            assert_eq!(contexts.get_mut_inclusive(orphan).err(), Some(ContextNotFound::new::<Depth>(orphan, true)));
+        }).unwrap();
+        assert_eq!(world.get::<Depth>(root), Some(&Depth(10)));
+        assert_eq!(world.get::<Depth>(leaf), Some(&Depth(20)));
+    }
+
+    #[test]
+    fn find_context_entity_matches_the_query() {
+        let mut world = World::new();
+        let [root, middle, leaf, orphan] = hierarchy(&mut world);
+        assert_eq!(find_context_entity::<Depth>(&world, leaf, false), Ok(root));
+        assert_eq!(find_context_entity::<Depth>(&world, leaf, true), Ok(leaf));
+        assert_eq!(find_context_entity::<Depth>(&world, middle, true), Ok(root));
+        assert!(find_context_entity::<Depth>(&world, orphan, true).is_err());
+    }
 }
@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use crate::context_system::ContextQuery;
+
+// Context components on an ancestor that tell the descendants consuming them when they change. A consumer
+// is marked with ContextConsumer<C> and gets ContextChanged<C> triggered on it when its nearest ancestor
+// with C changes, is removed or replaced, or when the consumer is added, or it or one of its ancestors is moved.
+//
+// app.add_context_provider::<PageModel>();
+// commands.spawn((Text::default(), ContextConsumer::<PageModel>::default()))
+//     .observe(|trigger: Trigger<ContextChanged<PageModel>>, models: Query<&PageModel>| { ... });
+#[derive(Component)]
+pub struct ContextConsumer<C: Component> {
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Component> Default for ContextConsumer<C> {
+    fn default() -> Self {
+        Self { marker: PhantomData }
+    }
+}
+
+#[derive(Event)]
+pub struct ContextChanged<C: Component> {
+    // The nearest ancestor with C now, None if there isn't one any more.
+    pub provider: Option<Entity>,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Component> ContextChanged<C> {
+    pub fn new(provider: Option<Entity>) -> Self {
+        Self { provider, marker: PhantomData }
+    }
+}
+
+pub trait ContextProviderAppExt {
+    fn add_context_provider<C: Component>(&mut self) -> &mut Self;
+}
+
+impl ContextProviderAppExt for App {
+    fn add_context_provider<C: Component>(&mut self) -> &mut Self {
+        self.add_systems(PostUpdate, notify_context_consumers_system::<C>)
+    }
+}
+
+// New consumers and entities with a new parent.
+type MovedFilter<C> = Or<(Added<ContextConsumer<C>>, Changed<Parent>)>;
+
+// Everything since the last run that can change which provider a consumer resolves to.
+#[derive(SystemParam)]
+struct ContextChanges<'w, 's, C: Component> {
+    changed_providers: Query<'w, 's, Entity, Changed<C>>,
+    removed_providers: RemovedComponents<'w, 's, C>,
+    moved_entities: Query<'w, 's, Entity, MovedFilter<C>>,
+}
+
+fn notify_context_consumers_system<C: Component>(
+    mut commands: Commands,
+    mut changes: ContextChanges<C>,
+    consumers: Query<(), With<ContextConsumer<C>>>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    contexts: ContextQuery<C>,
+) {
+    let mut notify: HashSet<Entity> = HashSet::new();
+
+    // Only the consumers that resolve to the changed provider, ones below a closer provider didn't see it.
+    for provider in changes.changed_providers.iter() {
+        for descendant in children.iter_descendants(provider) {
+            if consumers.contains(descendant) && contexts.entity(descendant).is_ok_and(|found| found == provider) {
+                notify.insert(descendant);
+            }
+        }
+    }
+
+    // Consumers that resolved to the removed provider now resolve to one above it, or to none.
+    for removed in changes.removed_providers.read() {
+        for descendant in children.iter_descendants(removed) {
+            if !consumers.contains(descendant) {
+                continue;
+            }
+            let below_removed = contexts
+                .entity(descendant)
+                .is_ok_and(|found| found != removed && parents.iter_ancestors(found).any(|ancestor| ancestor == removed));
+            if !below_removed {
+                notify.insert(descendant);
+            }
+        }
+    }
+
+    // A reparented entity takes its whole subtree along, the consumers below it may resolve elsewhere now.
+    // Subtrees below another moved entity are covered by that one's walk.
+    let moved: HashSet<Entity> = changes.moved_entities.iter().collect();
+    for &moved_root in moved.iter() {
+        if parents.iter_ancestors(moved_root).any(|ancestor| moved.contains(&ancestor)) {
+            continue;
+        }
+        for entity in std::iter::once(moved_root).chain(children.iter_descendants(moved_root)) {
+            if consumers.contains(entity) {
+                notify.insert(entity);
+            }
+        }
+    }
+
+    for consumer in notify {
+        let provider = contexts.entity(consumer).ok();
+        debug!(?consumer, ?provider, context = std::any::type_name::<C>(), "Context changed");
+        commands.trigger_targets(ContextChanged::<C>::new(provider), consumer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Model(u32);
+
+    #[derive(Resource, Default)]
+    struct Changes(Vec<(Entity, Option<Entity>)>);
+
+    // provider_a (Model) > page > consumer, and provider_b (Model) on its own.
+    fn app() -> (App, [Entity; 4]) {
+        let mut app = App::new();
+        app.add_context_provider::<Model>();
+        app.init_resource::<Changes>();
+        app.add_observer(|trigger: Trigger<ContextChanged<Model>>, mut changes: ResMut<Changes>| {
+            changes.0.push((trigger.entity(), trigger.event().provider));
+        });
+
+        let world = app.world_mut();
+        let provider_a = world.spawn(Model(1)).id();
+        let page = world.spawn_empty().set_parent(provider_a).id();
+        let consumer = world.spawn(ContextConsumer::<Model>::default()).set_parent(page).id();
+        let provider_b = world.spawn(Model(2)).id();
+
+        app.update();
+        assert_eq!(take_changes(&mut app), vec![(consumer, Some(provider_a))]);
+        (app, [provider_a, page, consumer, provider_b])
+    }
+
+    fn take_changes(app: &mut App) -> Vec<(Entity, Option<Entity>)> {
+        std::mem::take(&mut app.world_mut().resource_mut::<Changes>().0)
+    }
+
+    #[test]
+    fn provider_change_notifies_once() {
+        let (mut app, [provider_a, _, consumer, _]) = app();
+        app.world_mut().get_mut::<Model>(provider_a).unwrap().0 = 3;
+        app.update();
+        assert_eq!(take_changes(&mut app), vec![(consumer, Some(provider_a))]);
+
+        app.update();
+        assert!(take_changes(&mut app).is_empty());
+    }
+
+    #[test]
+    fn provider_removal_notifies_once() {
+        let (mut app, [provider_a, _, consumer, _]) = app();
+        app.world_mut().entity_mut(provider_a).remove::<Model>();
+        app.update();
+        assert_eq!(take_changes(&mut app), vec![(consumer, None)]);
+    }
+
+    #[test]
+    fn reparented_subtree_notifies_once() {
+        let (mut app, [_, page, consumer, provider_b]) = app();
+        app.world_mut().entity_mut(page).set_parent(provider_b);
+        app.update();
+        assert_eq!(take_changes(&mut app), vec![(consumer, Some(provider_b))]);
+    }
+}
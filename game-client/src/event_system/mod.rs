@@ -1,18 +1,36 @@
 use std::fmt::Debug;
+use bevy::ecs::system::SystemId;
+use bevy::ecs::traversal::Traversal;
 use bevy::prelude::*;
+use crate::event_system::tracer::{record_trace, TraceKind};
+
+pub mod scheduler;
+pub mod tracer;
 
 pub struct EventSystemPlugin;
 
 impl Plugin for EventSystemPlugin {
-    fn build(&self, _: &mut App) {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((tracer::EventTracerPlugin, scheduler::EventSchedulerPlugin));
     }
 }
 
+pub use macros::HandledEvent;
+
+// What the HandledEvent derive expands to, so the deriving crate doesn't need bevy under its own name.
+#[doc(hidden)]
+pub mod __macro_support {
+    pub use bevy::ecs::event::Event;
+}
+
+// Only this module can make one, so the handled flag can only be set through set_as_handled.
+pub struct HandledToken(());
+
+// Usually derived, see HandledEvent.
 pub trait HandledEventExt {
     fn is_handled(&self) -> bool;
 
-    // TODO : make this not exposed. we need set_as_handled to be exposed only.
-    fn set_handled(&mut self, handled: bool);
+    fn set_handled(&mut self, handled: bool, token: HandledToken);
 }
 
 #[derive(Debug, Component)]
@@ -31,33 +49,196 @@ where T: Event + Component
     const AUTO_PROPAGATE: bool = false;
 }
 
+// Capture phase of a bubbling event. Triggered on every entity of the propagation path from the root
+// down to the target before the event itself bubbles up from the target. Marking it handled stops
+// the capture phase and skips the bubble phase.
+#[derive(Debug, Component, HandledEvent)]
+pub struct Capture<T: Event> {
+    pub event: T,
+    // The entity the event is on its way to.
+    pub target: Entity,
+    pub(crate) is_handled: bool,
+}
+
+// What a fallback handler gets for an event no observer handled.
+#[derive(Debug, Clone)]
+pub struct UnhandledEvent<T> {
+    pub event: T,
+    // The entity the event was triggered on, PLACEHOLDER if it had none.
+    pub target: Entity,
+    // The target and the entities the event bubbles to from it, in order.
+    pub path: Vec<Entity>,
+}
+
+// A registered fallback handler. It returns true when it handled the event.
+pub type UnhandledEventFallback<T> = SystemId<In<UnhandledEvent<T>>, bool>;
+
+// Handlers that run for events of type T nobody handled, highest priority first. A handler that
+// handled the event stops the handlers after it.
+#[derive(Resource)]
+pub struct UnhandledEventFallbacks<T: Event> {
+    handlers: Vec<(i32, UnhandledEventFallback<T>)>,
+}
+
+impl<T: Event> Default for UnhandledEventFallbacks<T> {
+    fn default() -> Self {
+        Self { handlers: Vec::new() }
+    }
+}
+
+impl<T: Event> UnhandledEventFallbacks<T> {
+    fn insert(&mut self, priority: i32, handler: UnhandledEventFallback<T>) {
+        // Handlers with the same priority run in the order they were added.
+        let index = self.handlers.partition_point(|(other, _)| *other >= priority);
+        self.handlers.insert(index, (priority, handler));
+    }
+}
+
+// The entities an event of type T bubbles through from target, following T::Traversal.
+pub fn propagation_path<T: Event>(world: &mut World, target: Entity) -> Vec<Entity> {
+    if target == Entity::PLACEHOLDER {
+        return Vec::new();
+    }
+
+    let mut path = vec![target];
+    let mut query = world.query::<T::Traversal>();
+    while let Ok(item) = query.get(world, *path.last().unwrap()) {
+        let Some(next) = T::Traversal::traverse(item) else {
+            break;
+        };
+        if path.contains(&next) {
+            warn!(entity = ?next, "Event traversal loops back on itself");
+            break;
+        }
+        path.push(next);
+    }
+    path
+}
+
 pub trait UnhandledEventWorldExt {
-    fn trigger_unhandled_event<T: Event + Component + HandledEventExt>(&mut self, event: T, target: Entity);
+    fn trigger_with_capture<T: Event + HandledEventExt>(&mut self, event: T, target: Entity) -> T;
+
+    fn trigger_unhandled_event<T: Event + HandledEventExt + Clone>(&mut self, event: T, target: Entity);
+
+    fn add_unhandled_event_fallback<T: Event + Clone, M>(
+        &mut self,
+        priority: i32,
+        handler: impl IntoSystem<In<UnhandledEvent<T>>, bool, M> + 'static,
+    ) -> UnhandledEventFallback<T>;
+
+    fn remove_unhandled_event_fallback<T: Event + Clone>(&mut self, handler: UnhandledEventFallback<T>);
 }
 
 impl UnhandledEventWorldExt for World {
-    fn trigger_unhandled_event<T: Event + Component + HandledEventExt>(&mut self, mut event: T, target: Entity) {
-        if target != Entity::PLACEHOLDER {
+    // Runs the capture phase from the root down to target and then, unless a capture observer marked
+    // it handled, triggers the event on target so it bubbles. Returns the event as the observers left it.
+    fn trigger_with_capture<T: Event + HandledEventExt>(&mut self, event: T, target: Entity) -> T {
+        if target == Entity::PLACEHOLDER {
+            return event;
+        }
+
+        let mut capture = Capture {
+            event,
+            target,
+            is_handled: false,
+        };
+        for entity in propagation_path::<T>(self, target).into_iter().rev() {
+            self.trigger_targets_ref(&mut capture, entity);
+            if capture.is_handled {
+                break;
+            }
+        }
+
+        let mut event = capture.event;
+        if capture.is_handled {
+            event.set_handled(true, HandledToken(()));
+        } else {
             self.trigger_targets_ref(&mut event, target);
         }
+        event
+    }
 
-        if !event.is_handled() {
-            self.trigger_targets(
-                UnhandledInputEvent {
-                    event,
-                },
-                target,
-            );
+    // Triggers the event on target. If no observer along the way marks it handled, UnhandledInputEvent
+    // is triggered on target and then the fallback handlers registered for T run.
+    fn trigger_unhandled_event<T: Event + HandledEventExt + Clone>(&mut self, event: T, target: Entity) {
+        let event = self.trigger_with_capture(event, target);
+
+        if event.is_handled() {
+            return;
         }
+        record_trace::<T>(self, target, TraceKind::Unhandled);
+
+        self.trigger_targets(
+            UnhandledInputEvent {
+                event: event.clone(),
+            },
+            target,
+        );
+
+        let Some(fallbacks) = self.get_resource::<UnhandledEventFallbacks<T>>() else {
+            return;
+        };
+        let handlers = fallbacks.handlers.iter().map(|(_, handler)| *handler).collect::<Vec<_>>();
+        let unhandled = UnhandledEvent {
+            event,
+            target,
+            path: propagation_path::<T>(self, target),
+        };
+
+        for handler in handlers {
+            match self.run_system_with_input(handler, unhandled.clone()) {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => error!("Error: unhandled event fallback failed: {}", e),
+            }
+        }
+    }
+
+    fn add_unhandled_event_fallback<T: Event + Clone, M>(
+        &mut self,
+        priority: i32,
+        handler: impl IntoSystem<In<UnhandledEvent<T>>, bool, M> + 'static,
+    ) -> UnhandledEventFallback<T> {
+        let handler = self.register_system(handler);
+        self.get_resource_or_init::<UnhandledEventFallbacks<T>>().insert(priority, handler);
+        handler
+    }
+
+    fn remove_unhandled_event_fallback<T: Event + Clone>(&mut self, handler: UnhandledEventFallback<T>) {
+        if let Some(mut fallbacks) = self.get_resource_mut::<UnhandledEventFallbacks<T>>() {
+            fallbacks.handlers.retain(|(_, other)| *other != handler);
+        }
+        if let Err(e) = self.unregister_system(handler) {
+            warn!("Removing unhandled event fallback failed: {}", e);
+        }
+    }
+}
+
+pub trait UnhandledEventAppExt {
+    fn add_unhandled_event_fallback<T: Event + Clone, M>(
+        &mut self,
+        priority: i32,
+        handler: impl IntoSystem<In<UnhandledEvent<T>>, bool, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl UnhandledEventAppExt for App {
+    fn add_unhandled_event_fallback<T: Event + Clone, M>(
+        &mut self,
+        priority: i32,
+        handler: impl IntoSystem<In<UnhandledEvent<T>>, bool, M> + 'static,
+    ) -> &mut Self {
+        self.world_mut().add_unhandled_event_fallback(priority, handler);
+        self
     }
 }
 
 pub trait UnhandledEventCommandsExt {
-    fn trigger_unhandled_event<T: Event + Component + HandledEventExt>(&mut self, event: T, target: Entity);
+    fn trigger_unhandled_event<T: Event + HandledEventExt + Clone>(&mut self, event: T, target: Entity);
 }
 
 impl<'w, 's> UnhandledEventCommandsExt for Commands<'w,'s> {
-    fn trigger_unhandled_event<T: Event + Component + HandledEventExt>(&mut self, event: T, target: Entity) {
+    fn trigger_unhandled_event<T: Event + HandledEventExt + Clone>(&mut self, event: T, target: Entity) {
         self.queue(move |world: &mut World| {
             world.trigger_unhandled_event(event, target);
         })
@@ -65,6 +246,8 @@ impl<'w, 's> UnhandledEventCommandsExt for Commands<'w,'s> {
 }
 
 pub trait UnhandledEventTriggerExt {
+    // Stops the event here. Observers that should show up in the event trace call
+    // EventTracer::set_as_handled instead.
     fn set_as_handled(&mut self);
 }
 
@@ -75,6 +258,100 @@ where
 {
     fn set_as_handled(&mut self) {
         self.propagate(false);
-        self.event_mut().set_handled(true);
+        self.event_mut().set_handled(true, HandledToken(()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Clone, Debug, HandledEvent)]
+    #[event(traversal = Parent, auto_propagate)]
+    struct BubblingEvent {
+        is_handled: bool,
+    }
+
+    #[derive(Component, Clone, Debug, HandledEvent)]
+    struct MarkedEvent {
+        #[handled]
+        consumed: bool,
     }
-}
\ No newline at end of file
+
+    #[derive(Resource, Default)]
+    struct Seen(Vec<Entity>);
+
+    // root > parent > child
+    fn hierarchy(world: &mut World) -> (Entity, Entity, Entity) {
+        world.init_resource::<Seen>();
+        let root = world.spawn_empty().id();
+        let parent = world.spawn_empty().set_parent(root).id();
+        let child = world.spawn_empty().set_parent(parent).id();
+        (root, parent, child)
+    }
+
+    fn record_seen<T: Event>(trigger: Trigger<T>, mut seen: ResMut<Seen>) {
+        seen.0.push(trigger.entity());
+    }
+
+    #[test]
+    fn handled_flag_round_trips_through_bubbling() {
+        let mut world = World::new();
+        let (root, parent, child) = hierarchy(&mut world);
+        world.entity_mut(child).observe(record_seen::<BubblingEvent>);
+        world.entity_mut(parent).observe(|mut trigger: Trigger<BubblingEvent>, mut seen: ResMut<Seen>| {
+            seen.0.push(trigger.entity());
+            trigger.set_as_handled();
+        });
+        world.entity_mut(root).observe(record_seen::<BubblingEvent>);
+        world.flush();
+
+        let event = world.trigger_with_capture(BubblingEvent { is_handled: false }, child);
+        assert!(event.is_handled());
+        // Handling stops the bubbling.
+        assert_eq!(world.resource::<Seen>().0, vec![child, parent]);
+
+        world.resource_mut::<Seen>().0.clear();
+        let event = world.trigger_with_capture(BubblingEvent { is_handled: false }, root);
+        assert!(!event.is_handled());
+        assert_eq!(world.resource::<Seen>().0, vec![root]);
+    }
+
+    #[test]
+    fn handled_capture_skips_bubbling() {
+        let mut world = World::new();
+        let (root, parent, child) = hierarchy(&mut world);
+        world.entity_mut(root).observe(record_seen::<Capture<BubblingEvent>>);
+        world.entity_mut(parent).observe(|mut trigger: Trigger<Capture<BubblingEvent>>, mut seen: ResMut<Seen>| {
+            seen.0.push(trigger.entity());
+            trigger.set_as_handled();
+        });
+        world.entity_mut(child).observe(record_seen::<BubblingEvent>);
+        world.flush();
+
+        let event = world.trigger_with_capture(BubblingEvent { is_handled: false }, child);
+        assert!(event.is_handled());
+        assert_eq!(world.resource::<Seen>().0, vec![root, parent]);
+    }
+
+    #[test]
+    fn handled_attribute_picks_the_field() {
+        let mut world = World::new();
+        let (_, parent, child) = hierarchy(&mut world);
+        world.add_unhandled_event_fallback(0, |In(unhandled): In<UnhandledEvent<MarkedEvent>>, mut seen: ResMut<Seen>| {
+            seen.0.push(unhandled.target);
+            true
+        });
+        // No traversal by default, the parent never sees it.
+        world.entity_mut(parent).observe(|mut trigger: Trigger<MarkedEvent>| trigger.set_as_handled());
+        world.flush();
+
+        world.trigger_unhandled_event(MarkedEvent { consumed: false }, child);
+        assert_eq!(world.resource::<Seen>().0, vec![child]);
+
+        world.entity_mut(child).observe(|mut trigger: Trigger<MarkedEvent>| trigger.set_as_handled());
+        world.flush();
+        let event = world.trigger_with_capture(MarkedEvent { consumed: false }, child);
+        assert!(event.consumed);
+    }
+}
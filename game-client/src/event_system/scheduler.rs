@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use bevy::core::FrameCount;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+// Triggers events on a target later: after a delay, repeatedly, or after a number of frames. Scheduled
+// events can be cancelled through their handle and are dropped once their target is despawned.
+pub struct EventSchedulerPlugin;
+
+impl Plugin for EventSchedulerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScheduledEvents>();
+        app.add_systems(Update, scheduled_events_system);
+    }
+}
+
+// A repeating schedule that fell behind (e.g. after a long frame) catches up at most this many times per update.
+pub const MAX_REPEATS_PER_UPDATE: u32 = 10;
+
+// Numbered by the ScheduledEvents resource, so every world counts its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScheduledEventHandle(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventSchedule {
+    // Once, when the delay has passed.
+    After(Duration),
+    // Every interval until cancelled.
+    Every(Duration),
+    // Once, this many frames after the one it was scheduled in, counted by FrameCount. Frames(0) fires in
+    // this update and Frames(1) in the next one. Scheduled after the scheduler already ran this update,
+    // Frames(0) fires in the next one.
+    Frames(u32),
+}
+
+enum Countdown {
+    Timer(Timer),
+    Frames { scheduled_frame: u32, frames: u32 },
+}
+
+type TriggerFn = Box<dyn Fn(&mut World, Entity) + Send + Sync>;
+
+struct ScheduledEvent {
+    handle: ScheduledEventHandle,
+    target: Entity,
+    countdown: Countdown,
+    trigger: TriggerFn,
+}
+
+#[derive(Resource, Default)]
+pub struct ScheduledEvents {
+    events: Vec<ScheduledEvent>,
+    // Cancels that happen while the events are running, applied before anything else fires.
+    cancelled: HashSet<ScheduledEventHandle>,
+    // Atomic so a handle can be reserved through Res, before the event is inserted by a command.
+    next_handle: AtomicU64,
+}
+
+impl ScheduledEvents {
+    pub fn reserve_handle(&self) -> ScheduledEventHandle {
+        ScheduledEventHandle(self.next_handle.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn insert<E: Event + Clone>(&mut self, handle: ScheduledEventHandle, schedule: EventSchedule, event: E, target: Entity, frame: u32) {
+        let countdown = match schedule {
+            EventSchedule::After(delay) => Countdown::Timer(Timer::new(delay, TimerMode::Once)),
+            EventSchedule::Every(Duration::ZERO) => {
+                warn!(?handle, ?target, "Repeating event scheduled with a zero interval, ignoring it");
+                return;
+            }
+            EventSchedule::Every(interval) => Countdown::Timer(Timer::new(interval, TimerMode::Repeating)),
+            EventSchedule::Frames(frames) => Countdown::Frames { scheduled_frame: frame, frames },
+        };
+        self.events.push(ScheduledEvent {
+            handle,
+            target,
+            countdown,
+            trigger: Box::new(move |world, target| world.trigger_targets(event.clone(), target)),
+        });
+    }
+
+    pub fn cancel(&mut self, handle: ScheduledEventHandle) {
+        self.events.retain(|scheduled| scheduled.handle != handle);
+        self.cancelled.insert(handle);
+    }
+
+    pub fn is_scheduled(&self, handle: ScheduledEventHandle) -> bool {
+        self.events.iter().any(|scheduled| scheduled.handle == handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+pub trait ScheduleEventWorldExt {
+    fn schedule_event<E: Event + Clone>(&mut self, schedule: EventSchedule, event: E, target: Entity) -> ScheduledEventHandle;
+
+    fn cancel_scheduled_event(&mut self, handle: ScheduledEventHandle);
+}
+
+impl ScheduleEventWorldExt for World {
+    fn schedule_event<E: Event + Clone>(&mut self, schedule: EventSchedule, event: E, target: Entity) -> ScheduledEventHandle {
+        let handle = self.get_resource_or_init::<ScheduledEvents>().reserve_handle();
+        insert_scheduled_event(self, handle, schedule, event, target);
+        handle
+    }
+
+    fn cancel_scheduled_event(&mut self, handle: ScheduledEventHandle) {
+        if let Some(mut scheduled_events) = self.get_resource_mut::<ScheduledEvents>() {
+            scheduled_events.cancel(handle);
+        }
+    }
+}
+
+// Schedules events from systems and observers. The handle is returned right away, the event is
+// inserted when the commands are applied.
+#[derive(SystemParam)]
+pub struct EventScheduler<'w, 's> {
+    commands: Commands<'w, 's>,
+    scheduled_events: Res<'w, ScheduledEvents>,
+}
+
+impl EventScheduler<'_, '_> {
+    pub fn schedule_event<E: Event + Clone>(&mut self, schedule: EventSchedule, event: E, target: Entity) -> ScheduledEventHandle {
+        let handle = self.scheduled_events.reserve_handle();
+        self.commands.queue(move |world: &mut World| {
+            insert_scheduled_event(world, handle, schedule, event, target);
+        });
+        handle
+    }
+
+    pub fn trigger_after<E: Event + Clone>(&mut self, delay: Duration, event: E, target: Entity) -> ScheduledEventHandle {
+        self.schedule_event(EventSchedule::After(delay), event, target)
+    }
+
+    pub fn trigger_every<E: Event + Clone>(&mut self, interval: Duration, event: E, target: Entity) -> ScheduledEventHandle {
+        self.schedule_event(EventSchedule::Every(interval), event, target)
+    }
+
+    pub fn trigger_after_frames<E: Event + Clone>(&mut self, frames: u32, event: E, target: Entity) -> ScheduledEventHandle {
+        self.schedule_event(EventSchedule::Frames(frames), event, target)
+    }
+
+    pub fn cancel_scheduled_event(&mut self, handle: ScheduledEventHandle) {
+        self.commands.queue(move |world: &mut World| {
+            world.cancel_scheduled_event(handle);
+        });
+    }
+}
+
+fn insert_scheduled_event<E: Event + Clone>(world: &mut World, handle: ScheduledEventHandle, schedule: EventSchedule, event: E, target: Entity) {
+    let frame = world.get_resource::<FrameCount>().map_or(0, |frame_count| frame_count.0);
+    world.get_resource_or_init::<ScheduledEvents>().insert(handle, schedule, event, target, frame);
+}
+
+fn scheduled_events_system(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let frame = world.get_resource::<FrameCount>().map_or(0, |frame_count| frame_count.0);
+    let mut scheduled_events = std::mem::take(&mut world.resource_mut::<ScheduledEvents>().events);
+
+    let mut index = 0;
+    while index < scheduled_events.len() {
+        let scheduled = &mut scheduled_events[index];
+        if world.get_entity(scheduled.target).is_err() {
+            debug!(entity = ?scheduled.target, handle = ?scheduled.handle, "Scheduled event target despawned, cancelling");
+            scheduled_events.remove(index);
+            continue;
+        }
+
+        let (times, done) = match &mut scheduled.countdown {
+            Countdown::Timer(timer) => {
+                timer.tick(delta);
+                (
+                    timer.times_finished_this_tick().min(MAX_REPEATS_PER_UPDATE),
+                    timer.finished() && timer.mode() == TimerMode::Once,
+                )
+            }
+            Countdown::Frames { scheduled_frame, frames } => {
+                if frame.wrapping_sub(*scheduled_frame) >= *frames { (1, true) } else { (0, false) }
+            }
+        };
+
+        let handle = scheduled.handle;
+        let target = scheduled.target;
+        for _ in 0..times {
+            // An earlier trigger may have cancelled this one or despawned its target.
+            if world.resource::<ScheduledEvents>().cancelled.contains(&handle) || world.get_entity(target).is_err() {
+                break;
+            }
+            (scheduled_events[index].trigger)(world, target);
+        }
+
+        if done {
+            scheduled_events.remove(index);
+        } else {
+            index += 1;
+        }
+    }
+
+    // Events scheduled while these ran go after them.
+    let mut resource = world.resource_mut::<ScheduledEvents>();
+    let cancelled = std::mem::take(&mut resource.cancelled);
+    scheduled_events.retain(|scheduled| !cancelled.contains(&scheduled.handle));
+    scheduled_events.append(&mut resource.events);
+    resource.events = scheduled_events;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::time::TimeUpdateStrategy;
+    use super::*;
+
+    #[derive(Event, Clone)]
+    struct Ping;
+
+    #[derive(Resource, Default)]
+    struct Pings(u32);
+
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, EventSchedulerPlugin));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+        app.init_resource::<Pings>();
+        let target = app.world_mut()
+            .spawn_empty()
+            .observe(|_: Trigger<Ping>, mut pings: ResMut<Pings>| pings.0 += 1)
+            .id();
+        app.world_mut().flush();
+        (app, target)
+    }
+
+    fn pings(app: &App) -> u32 {
+        app.world().resource::<Pings>().0
+    }
+
+    #[test]
+    fn zero_frames_fire_this_update_and_one_frame_the_next() {
+        let (mut app, target) = app();
+        app.world_mut().schedule_event(EventSchedule::Frames(0), Ping, target);
+        app.update();
+        assert_eq!(pings(&app), 1);
+
+        app.world_mut().schedule_event(EventSchedule::Frames(1), Ping, target);
+        app.update();
+        assert_eq!(pings(&app), 1);
+        app.update();
+        assert_eq!(pings(&app), 2);
+        assert!(app.world().resource::<ScheduledEvents>().is_empty());
+    }
+
+    #[test]
+    fn repeats_are_capped_per_update() {
+        let (mut app, target) = app();
+        app.world_mut().schedule_event(EventSchedule::Every(Duration::from_millis(1)), Ping, target);
+        // Time doesn't advance in the first update.
+        app.update();
+        assert_eq!(pings(&app), 0);
+        // 100 intervals passed.
+        app.update();
+        assert_eq!(pings(&app), MAX_REPEATS_PER_UPDATE);
+    }
+
+    #[test]
+    fn zero_interval_is_rejected() {
+        let (mut app, target) = app();
+        let handle = app.world_mut().schedule_event(EventSchedule::Every(Duration::ZERO), Ping, target);
+        assert!(!app.world().resource::<ScheduledEvents>().is_scheduled(handle));
+    }
+
+    #[test]
+    fn handles_are_not_entities() {
+        let (mut app, target) = app();
+        let entities = app.world().entities().len();
+        let first = app.world_mut().schedule_event(EventSchedule::Every(Duration::from_secs(1)), Ping, target);
+        let second = app.world_mut()
+            .run_system_once(move |mut scheduler: EventScheduler| scheduler.trigger_every(Duration::from_secs(1), Ping, target))
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(app.world().resource::<ScheduledEvents>().is_scheduled(second));
+        assert_eq!(app.world().entities().len(), entities);
+
+        app.world_mut().cancel_scheduled_event(first);
+        app.world_mut().cancel_scheduled_event(second);
+        assert!(app.world().resource::<ScheduledEvents>().is_empty());
+    }
+}
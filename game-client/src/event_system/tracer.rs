@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::panic::Location;
+use std::path::PathBuf;
+use bevy::core::FrameCount;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use crate::event_system::{HandledEventExt, UnhandledEventTriggerExt};
+
+// Opt-in tracing of how events travel through the hierarchy. Every hop of a traced event is recorded,
+// along with the observers that report themselves through an EventTracer param and the place
+// EventTracer::set_as_handled was called from.
+//
+// GAME_TRACE_EVENTS=1 shows the trace in an on-screen panel, GAME_TRACE_EVENTS_LOG=<path> appends it
+// to a file. Both can also be switched at runtime through the EventTrace resource.
+pub struct EventTracerPlugin;
+
+pub const TRACE_EVENTS_ENV: &str = "GAME_TRACE_EVENTS";
+pub const TRACE_EVENTS_LOG_ENV: &str = "GAME_TRACE_EVENTS_LOG";
+
+impl Plugin for EventTracerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EventTrace {
+            overlay: std::env::var(TRACE_EVENTS_ENV).is_ok_and(|value| !value.is_empty() && value != "0"),
+            log_path: std::env::var(TRACE_EVENTS_LOG_ENV).ok().map(PathBuf::from),
+            ..default()
+        });
+
+        app.add_systems(Last, (event_trace_collect_system, event_trace_overlay_system).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    // The event arrived at the entity.
+    Hop,
+    ObserverRan(&'static Location<'static>),
+    Handled(&'static Location<'static>),
+    // Nothing along the path handled the event, fallbacks run next.
+    Unhandled,
+}
+
+#[derive(Clone, Debug)]
+struct PendingTrace {
+    event: &'static str,
+    entity: Entity,
+    kind: TraceKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    pub frame: u32,
+    pub event: String,
+    pub entity: Entity,
+    pub entity_name: Option<String>,
+    pub kind: TraceKind,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {} @ {}", self.frame, self.event, self.entity)?;
+        if let Some(name) = &self.entity_name {
+            write!(f, " ({})", name)?;
+        }
+        match self.kind {
+            TraceKind::Hop => write!(f, " hop"),
+            TraceKind::ObserverRan(location) => write!(f, " observer {}:{}", location.file(), location.line()),
+            TraceKind::Handled(location) => write!(f, " handled by {}:{}", location.file(), location.line()),
+            TraceKind::Unhandled => write!(f, " unhandled"),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct EventTrace {
+    // Recorded during the frame, turned into entries (with frame and names) when the frame ends.
+    pending: Vec<PendingTrace>,
+    entries: VecDeque<TraceEntry>,
+    // Oldest entries are dropped past this.
+    pub capacity: usize,
+    pub overlay: bool,
+    pub log_path: Option<PathBuf>,
+}
+
+impl Default for EventTrace {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            entries: VecDeque::new(),
+            capacity: 200,
+            overlay: false,
+            log_path: None,
+        }
+    }
+}
+
+impl EventTrace {
+    pub fn is_enabled(&self) -> bool {
+        self.overlay || self.log_path.is_some()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Does nothing while tracing is off.
+    pub fn record<T: ?Sized>(&mut self, entity: Entity, kind: TraceKind) {
+        if self.is_enabled() {
+            self.pending.push(PendingTrace {
+                event: std::any::type_name::<T>(),
+                entity,
+                kind,
+            });
+        }
+    }
+}
+
+// For observers to report themselves in the trace:
+//
+// fn my_observer(mut trigger: Trigger<MappedInputEvent>, mut tracer: EventTracer) {
+//     tracer.observer_ran(&trigger);
+//     ...
+//     tracer.set_as_handled(&mut trigger);
+// }
+//
+// The EventTrace resource is only changed while tracing is on, and missing (e.g. without the plugin)
+// means nothing is traced.
+#[derive(SystemParam)]
+pub struct EventTracer<'w> {
+    event_trace: Option<ResMut<'w, EventTrace>>,
+}
+
+impl EventTracer<'_> {
+    fn record<T: ?Sized>(&mut self, entity: Entity, kind: TraceKind) {
+        // Checked before DerefMut so a disabled trace isn't marked changed.
+        if let Some(event_trace) = self.event_trace.as_mut() {
+            if event_trace.is_enabled() {
+                event_trace.record::<T>(entity, kind);
+            }
+        }
+    }
+
+    // Records that the calling observer ran for this hop of the event.
+    #[track_caller]
+    pub fn observer_ran<T: Event>(&mut self, trigger: &Trigger<'_, T>) {
+        self.record::<T>(trigger.entity(), TraceKind::ObserverRan(Location::caller()));
+    }
+
+    // Trigger::set_as_handled that also records where the event was handled.
+    #[track_caller]
+    pub fn set_as_handled<T: Event + Component + HandledEventExt>(&mut self, trigger: &mut Trigger<'_, T>) {
+        self.record::<T>(trigger.entity(), TraceKind::Handled(Location::caller()));
+        trigger.set_as_handled();
+    }
+}
+
+// For code with world access, e.g. commands.
+pub fn record_trace<T: ?Sized>(world: &mut World, entity: Entity, kind: TraceKind) {
+    if let Some(mut event_trace) = world.get_resource_mut::<EventTrace>() {
+        if event_trace.is_enabled() {
+            event_trace.record::<T>(entity, kind);
+        }
+    }
+}
+
+pub trait TraceEventAppExt {
+    // Records every hop of T while tracing is on.
+    fn trace_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl TraceEventAppExt for App {
+    fn trace_event<T: Event>(&mut self) -> &mut Self {
+        // Observers without a target run for every entity the event reaches.
+        self.add_observer(|trigger: Trigger<T>, mut tracer: EventTracer| {
+            tracer.record::<T>(trigger.entity(), TraceKind::Hop);
+        });
+        self
+    }
+}
+
+// Drops the module paths, game_client::ui::Foo<game_client::Bar> becomes Foo<Bar>.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (index, character) in name.char_indices() {
+        if matches!(character, '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | '&' | ';') {
+            let segment = &name[segment_start..index];
+            short.push_str(segment.rsplit("::").next().unwrap_or(segment));
+            short.push(character);
+            segment_start = index + character.len_utf8();
+        }
+    }
+    let segment = &name[segment_start..];
+    short.push_str(segment.rsplit("::").next().unwrap_or(segment));
+    short
+}
+
+fn event_trace_collect_system(mut event_trace: ResMut<EventTrace>, names: Query<&Name>, frame_count: Res<FrameCount>) {
+    if event_trace.pending.is_empty() {
+        return;
+    }
+    let pending = std::mem::take(&mut event_trace.pending);
+    if !event_trace.is_enabled() {
+        return;
+    }
+
+    let entries = pending
+        .into_iter()
+        .map(|trace| TraceEntry {
+            frame: frame_count.0,
+            event: short_type_name(trace.event),
+            entity: trace.entity,
+            entity_name: names.get(trace.entity).ok().map(|name| name.to_string()),
+            kind: trace.kind,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(path) = event_trace.log_path.as_ref() {
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| entries.iter().try_for_each(|entry| writeln!(file, "{}", entry)));
+        if let Err(e) = written {
+            error!("Error: writing event trace to {:?} failed: {}", path, e);
+        }
+    }
+
+    for entry in entries {
+        event_trace.entries.push_back(entry);
+    }
+    while event_trace.entries.len() > event_trace.capacity {
+        event_trace.entries.pop_front();
+    }
+}
+
+#[derive(Component)]
+struct EventTraceOverlay;
+
+#[derive(Component)]
+struct EventTraceOverlayText;
+
+const OVERLAY_LINES: usize = 30;
+
+fn event_trace_overlay_system(
+    mut commands: Commands,
+    event_trace: Res<EventTrace>,
+    overlays: Query<Entity, With<EventTraceOverlay>>,
+    mut overlay_texts: Query<&mut Text, With<EventTraceOverlayText>>,
+) {
+    if !event_trace.overlay {
+        for overlay in overlays.iter() {
+            commands.entity(overlay).despawn_recursive();
+        }
+        return;
+    }
+
+    if overlays.is_empty() {
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    max_width: Val::Percent(50.0),
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.75)),
+                GlobalZIndex(i32::MAX),
+                PickingBehavior::IGNORE,
+                EventTraceOverlay,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::default(),
+                    TextFont::from_font_size(12.0),
+                    PickingBehavior::IGNORE,
+                    EventTraceOverlayText,
+                ));
+            });
+        return;
+    }
+
+    if !event_trace.is_changed() {
+        return;
+    }
+
+    let skip = event_trace.entries.len().saturating_sub(OVERLAY_LINES);
+    let lines = event_trace
+        .entries()
+        .skip(skip)
+        .map(|entry| entry.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut text in overlay_texts.iter_mut() {
+        text.0 = lines.clone();
+    }
+}
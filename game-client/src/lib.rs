@@ -1,5 +1,8 @@
 #![feature(let_chains)]
 
+// Lets the derives of the macros crate name this crate the same way inside and outside of it.
+extern crate self as game_client;
+
 pub mod ui;
 pub mod scene_system;
 pub mod asset_util;
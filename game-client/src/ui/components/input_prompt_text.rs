@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use crate::ui::components::text_creator::TextCreator;
+use crate::ui::input::prompts::InputPrompts;
+
+pub struct InputPromptTextPlugin;
+
+impl Plugin for InputPromptTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<InputPromptText>();
+        app.add_systems(PostUpdate, input_prompt_text_system);
+    }
+}
+
+// Text with the bindings of actions filled in, e.g. "Press [ui_confirm] to continue".
+// Written to the TextCreator of the entity, or to its Text if it has none.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct InputPromptText {
+    pub template: String,
+}
+
+impl InputPromptText {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self { template: template.into() }
+    }
+}
+
+fn input_prompt_text_system(
+    mut query: Query<(Ref<InputPromptText>, Option<&mut TextCreator>, Option<&mut Text>)>,
+    input_prompts: InputPrompts,
+) {
+    let prompts_changed = input_prompts.is_changed();
+    for (prompt_text, text_creator, text) in query.iter_mut() {
+        if !prompts_changed && !prompt_text.is_changed() {
+            continue;
+        }
+
+        let formatted = input_prompts.format(&prompt_text.template);
+        if let Some(mut text_creator) = text_creator {
+            if text_creator.text != formatted {
+                text_creator.text = formatted.into();
+            }
+        } else if let Some(mut text) = text {
+            if text.0 != formatted {
+                text.0 = formatted;
+            }
+        }
+    }
+}
@@ -1,10 +1,12 @@
 pub mod text_input;
 pub mod ui_elements;
 pub mod text_creator;
+pub mod input_prompt_text;
 
 use bevy::prelude::*;
 use crate::ui::components::text_creator::TextCreatorPlugin;
 use crate::ui::components::text_input::TextInputPlugin;
+use crate::ui::components::input_prompt_text::InputPromptTextPlugin;
 
 pub struct ComponentsPlugin;
 
@@ -16,6 +18,9 @@ impl Plugin for ComponentsPlugin {
         app.add_plugins(
             TextInputPlugin,
         );
+        app.add_plugins(
+            InputPromptTextPlugin,
+        );
     }
 }
 
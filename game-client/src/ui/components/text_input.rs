@@ -4,10 +4,10 @@ use bevy::ecs::world::DeferredWorld;
 use bevy::input::keyboard::Key;
 use bevy::prelude::*;
 use smol_str::SmolStr;
-use crate::event_system::{HandledEventExt, UnhandledEventTriggerExt};
+use crate::event_system::tracer::EventTracer;
 use crate::ui::components::text_creator::TextCreator;
-use crate::ui::input::focus::InputFocus;
-use crate::ui::input::input_map::MappedInputEvent;
+use crate::ui::input::focus::{InputFocus, ReleaseFocusEvent};
+use crate::ui::input::input_map::{InputMaps, MappedInputEvent, TEXT_ENTRY_CONTEXT};
 
 pub struct TextInputPlugin;
 
@@ -20,6 +20,7 @@ impl Plugin for TextInputPlugin {
 
         // Systems
         // app.add_systems(PostUpdate, text_update_system);
+        app.add_systems(PostUpdate, text_entry_context_system.run_if(resource_changed::<InputFocus>));
 
         // Observers
         app.add_observer(basic_text_input_observer);
@@ -57,8 +58,38 @@ pub struct BasicTextInput {
     pub placeholder_text: String,
 }
 
-fn basic_text_input_observer(mut trigger: Trigger<MappedInputEvent>, mut commands: Commands) {
+// Keeps the text entry input context on the stack while a BasicTextInput is focused.
+fn text_entry_context_system(
+    input_focus: Res<InputFocus>,
+    text_inputs: Query<(), With<BasicTextInput>>,
+    mut input_maps: ResMut<InputMaps>,
+) {
+    let text_input_focused = input_focus.0.is_some_and(|entity| text_inputs.contains(entity));
+    let context_active = input_maps.is_context_active(TEXT_ENTRY_CONTEXT);
+
+    if text_input_focused && !context_active {
+        input_maps.push_context(TEXT_ENTRY_CONTEXT);
+    } else if !text_input_focused && context_active {
+        input_maps.remove_active_context(TEXT_ENTRY_CONTEXT);
+    }
+}
+
+fn basic_text_input_observer(
+    mut trigger: Trigger<MappedInputEvent>,
+    mut tracer: EventTracer,
+    mut commands: Commands,
+    text_inputs: Query<(), With<BasicTextInput>>,
+) {
+    tracer.observer_ran(&trigger);
     let entity = trigger.entity();
+    if trigger.event().keys.contains("ui_text_release") && text_inputs.contains(entity) {
+        commands.send_event(ReleaseFocusEvent::ToParent {
+            entity,
+            allow_none: true,
+        });
+        tracer.set_as_handled(&mut trigger);
+        return;
+    }
     if let Some(keyboard_input) = trigger.event().keyboard_input.as_ref() {
         if !keyboard_input.repeat && !keyboard_input.state.is_pressed() {
             return;
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
+use bevy::scene::{SceneInstanceReady, SceneSpawner};
 use crate::event_system::{UnhandledEventWorldExt, UnhandledInputEvent};
+use crate::event_system::tracer::EventTracer;
 use crate::ui::input::input_map::*;
 
 pub struct UiFocusPlugin;
@@ -10,12 +12,15 @@ impl Plugin for UiFocusPlugin {
         app.add_event::<FocusReleased>();
         app.add_observer(click_focus_change_observer);
         app.add_observer(default_focus_release_input_observer);
+        app.add_observer(directional_focus_input_observer);
         app.add_observer(focus_release_despawn_system);
+        app.add_observer(auto_focus_scene_ready_observer);
         app.add_systems(PostUpdate, focus_release_system);
         app.add_systems(PreUpdate, keyboard_event_system);
         // app.add_systems(Last, focus_release_despawn_system);
         app.add_event::<ReleaseFocusEvent>();
         app.register_type::<InputFocusPolicy>();
+        app.register_type::<AutoFocus>();
         app.insert_resource(InputFocus(None));
         app.add_systems(Last, focus_change_propagator_system);
     }
@@ -51,6 +56,71 @@ pub enum ReleaseFocusEvent {
 #[derive(Resource, Clone, Debug)]
 pub struct InputFocus(pub Option<Entity>);
 
+// Marks the element that takes focus once the scene instance it belongs to is spawned.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct AutoFocus;
+
+// Scenes spawned as children of an entity with this component don't apply AutoFocus on their own.
+// The owner (e.g. UiNavigation) decides which element gets focus.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ManagedSceneFocus;
+
+// Location of an element relative to a root entity. Unlike an Entity it stays valid after the subtree
+// is despawned and spawned again from the same scene.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FocusPath {
+    Name(String),
+    ChildIndices(Vec<usize>),
+}
+
+impl FocusPath {
+    // Prefers the Name of the entity when it has one, otherwise falls back to child indices.
+    pub fn from_entity(
+        entity: Entity,
+        root: Entity,
+        parents: &Query<&Parent>,
+        children: &Query<&Children>,
+        names: &Query<&Name>,
+    ) -> Option<Self> {
+        if let Ok(name) = names.get(entity) {
+            return Some(FocusPath::Name(name.as_str().to_string()));
+        }
+
+        let mut indices = Vec::new();
+        let mut current = entity;
+        while current != root {
+            let parent = parents.get(current).ok()?.get();
+            let index = children.get(parent).ok()?.iter().position(|child| *child == current)?;
+            indices.push(index);
+            current = parent;
+        }
+        indices.reverse();
+
+        Some(FocusPath::ChildIndices(indices))
+    }
+
+    pub fn resolve(
+        &self,
+        root: Entity,
+        children: &Query<&Children>,
+        names: &Query<&Name>,
+    ) -> Option<Entity> {
+        match self {
+            FocusPath::Name(name) => children
+                .iter_descendants(root)
+                .find(|descendant| names.get(*descendant).is_ok_and(|n| n.as_str() == name)),
+            FocusPath::ChildIndices(indices) => {
+                let mut current = root;
+                for index in indices {
+                    current = *children.get(current).ok()?.get(*index)?;
+                }
+                Some(current)
+            }
+        }
+    }
+}
+
 
 // Manages focus change based on click.
 pub fn click_focus_change_observer(trigger: Trigger<Pointer<Click>>, query: Query<Entity>, _: ResMut<InputFocus>, mut commands: Commands) {
@@ -101,7 +171,36 @@ impl SetNextInputFocusTrait for World {
 }
 
 
-pub fn default_focus_release_input_observer(trigger: Trigger<UnhandledInputEvent<MappedInputEvent>>, mut event_writer: EventWriter<ReleaseFocusEvent>) {
+pub fn auto_focus_scene_ready_observer(
+    trigger: Trigger<SceneInstanceReady>,
+    scene_spawner: Res<SceneSpawner>,
+    parents: Query<&Parent>,
+    managed: Query<(), With<ManagedSceneFocus>>,
+    auto_focus: Query<(), With<AutoFocus>>,
+    mut commands: Commands,
+) {
+    let scene_entity = trigger.entity();
+    if let Ok(parent) = parents.get(scene_entity) {
+        if managed.contains(parent.get()) {
+            return;
+        }
+    }
+
+    let mut targets = scene_spawner
+        .iter_instance_entities(trigger.event().instance_id)
+        .filter(|entity| auto_focus.contains(*entity));
+
+    if let Some(target) = targets.next() {
+        if targets.next().is_some() {
+            warn!(entity = ?target, "Scene instance has more than one AutoFocus element");
+        }
+        debug!(entity = ?target, "Auto focus");
+        commands.queue_next_focus(Some(target));
+    }
+}
+
+pub fn default_focus_release_input_observer(trigger: Trigger<UnhandledInputEvent<MappedInputEvent>>, mut tracer: EventTracer, mut event_writer: EventWriter<ReleaseFocusEvent>) {
+    tracer.observer_ran(&trigger);
     if trigger.event().event.keys.contains(&"ui_focus_release".to_string()) {
         debug!("Focus release key event");
         event_writer.send(ReleaseFocusEvent::ToParent {
@@ -111,6 +210,74 @@ pub fn default_focus_release_input_observer(trigger: Trigger<UnhandledInputEvent
     }
 }
 
+// Moves focus to the closest focusable element in the direction of ui_up, ui_down, ui_left or ui_right.
+// Elements that contain other focusable elements (e.g. navigations) are skipped.
+pub fn directional_focus_input_observer(
+    trigger: Trigger<UnhandledInputEvent<MappedInputEvent>>,
+    mut tracer: EventTracer,
+    focusables: Query<(Entity, &InputFocusPolicy, &GlobalTransform, &InheritedVisibility)>,
+    parents: Query<&Parent>,
+    input_focus: Res<InputFocus>,
+    mut commands: Commands,
+) {
+    tracer.observer_ran(&trigger);
+    let keys = &trigger.event().event.keys;
+    let direction = if keys.contains("ui_up") {
+        Vec2::NEG_Y
+    } else if keys.contains("ui_down") {
+        Vec2::Y
+    } else if keys.contains("ui_left") {
+        Vec2::NEG_X
+    } else if keys.contains("ui_right") {
+        Vec2::X
+    } else {
+        return;
+    };
+
+    let focusable = focusables
+        .iter()
+        .filter(|(_, policy, _, visibility)| **policy == InputFocusPolicy::All && visibility.get())
+        .map(|(entity, _, transform, _)| (entity, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+
+    let containers = focusable
+        .iter()
+        .flat_map(|(entity, _)| parents.iter_ancestors(*entity))
+        .collect::<std::collections::HashSet<_>>();
+
+    let candidates = focusable
+        .into_iter()
+        .filter(|(entity, _)| !containers.contains(entity) && Some(*entity) != input_focus.0);
+
+    let current_position = input_focus.0
+        .and_then(|entity| focusables.get(entity).ok())
+        .map(|(_, _, transform, _)| transform.translation().truncate());
+
+    let next_focus = match current_position {
+        Some(current_position) => candidates
+            .filter_map(|(entity, position)| {
+                let offset = position - current_position;
+                let along = offset.dot(direction);
+                if along <= 0.0 {
+                    return None;
+                }
+                let across = (offset - direction * along).length();
+                Some((entity, along + across * 2.0))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity),
+        // Nothing focused yet, start from the top left element.
+        None => candidates
+            .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+            .map(|(entity, _)| entity),
+    };
+
+    if let Some(next_focus) = next_focus {
+        debug!(entity = ?next_focus, ?direction, "Directional focus change");
+        commands.queue_next_focus(Some(next_focus));
+    }
+}
+
 pub fn focus_release_despawn_system(
     trigger: Trigger<OnRemove, InputFocusPolicy>,
     parents: Query<&Parent>,
@@ -162,26 +329,16 @@ pub fn focus_release_system(world: &mut World) {
                     continue;
                 }
 
-                let mut parent_focused = false;
-                loop {
-                    let current_child = *entity;
-                    if let Some(policy) = world.get::<InputFocusPolicy>(current_child) {
-                        match policy {
-                            InputFocusPolicy::All => {
-                                if world.set_next_input_focus(Some(current_child)).is_ok() {
-                                    parent_focused = true;
-                                    break;
-                                }
-                            }
-                            _ => {
-                            continue;
-                            }
-                        }
-                    }
+                // The closest ancestor that accepts focus takes it over.
+                let ancestors = std::iter::successors(world.get::<Parent>(*entity).map(Parent::get), |ancestor| {
+                    world.get::<Parent>(*ancestor).map(Parent::get)
+                }).collect::<Vec<_>>();
 
-                    if let Some(_) = world.get::<Parent>(current_child) {
-                        // current_child = parent_entity.get();
-                    } else {
+                let mut parent_focused = false;
+                for ancestor in ancestors {
+                    if world.get::<InputFocusPolicy>(ancestor) == Some(&InputFocusPolicy::All)
+                        && world.set_next_input_focus(Some(ancestor)).is_ok() {
+                        parent_focused = true;
                         break;
                     }
                 }
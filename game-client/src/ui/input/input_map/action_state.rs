@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::ui::input::input_map::{InputSource, InputValue, MappedInputEvent};
+
+#[derive(Clone, Debug, Default)]
+pub struct ActionData {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    pub pressed_at: Duration,
+    pub hold_duration: Duration,
+    // Controls currently holding the action down. The action is released when the last one is.
+    sources: HashSet<(Option<Entity>, InputSource)>,
+}
+
+// Per key state of the mapped input, updated every frame after the input is mapped.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    actions: HashMap<String, ActionData>,
+    // Analog actions of the active input contexts, recomputed every frame.
+    axes: HashMap<String, f32>,
+    dual_axes: HashMap<String, Vec2>,
+}
+
+impl ActionState {
+    pub fn get(&self, action: &str) -> Option<&ActionData> {
+        self.actions.get(action)
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.get(action).is_some_and(|data| data.pressed)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.get(action).is_some_and(|data| data.just_pressed)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.get(action).is_some_and(|data| data.just_released)
+    }
+
+    pub fn hold_duration(&self, action: &str) -> Duration {
+        self.get(action).map_or(Duration::ZERO, |data| data.hold_duration)
+    }
+
+    // Zero for axes that aren't bound in an active context.
+    pub fn axis(&self, action: &str) -> f32 {
+        self.axes.get(action).copied().unwrap_or(0.0)
+    }
+
+    pub fn axis_pair(&self, action: &str) -> Vec2 {
+        self.dual_axes.get(action).copied().unwrap_or(Vec2::ZERO)
+    }
+
+    pub(crate) fn set_analog(&mut self, axes: HashMap<String, f32>, dual_axes: HashMap<String, Vec2>) {
+        self.axes = axes;
+        self.dual_axes = dual_axes;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ActionData)> {
+        self.actions.iter()
+    }
+
+    fn press(&mut self, action: &str, source: (Option<Entity>, InputSource), now: Duration) {
+        let data = self.actions.entry(action.to_string()).or_default();
+        if !data.pressed {
+            data.pressed = true;
+            data.just_pressed = true;
+            data.pressed_at = now;
+            data.hold_duration = Duration::ZERO;
+        }
+        data.sources.insert(source);
+    }
+
+    fn release_source(&mut self, source: &(Option<Entity>, InputSource)) {
+        for data in self.actions.values_mut() {
+            if data.sources.remove(source) && data.sources.is_empty() {
+                data.pressed = false;
+                data.just_released = true;
+            }
+        }
+    }
+}
+
+pub fn action_state_system(
+    mut mapped_events: EventReader<MappedInputEvent>,
+    mut action_state: ResMut<ActionState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    for data in action_state.actions.values_mut() {
+        data.just_pressed = false;
+        data.just_released = false;
+    }
+
+    for event in mapped_events.read() {
+        let Some(input_value) = event.input_value.as_ref() else {
+            continue;
+        };
+        if event.repeat {
+            continue;
+        }
+        let source = (event.gamepad, input_value.source());
+
+        if input_value.is_pressed() {
+            for key in event.keys.iter() {
+                action_state.press(key, source.clone(), now);
+            }
+            // Wheel steps and gestures have no release, they are pressed for a single frame.
+            if let InputValue::MouseWheel(_) | InputValue::Gesture(_) = input_value {
+                action_state.release_source(&source);
+            }
+        } else {
+            action_state.release_source(&source);
+        }
+    }
+
+    for data in action_state.actions.values_mut() {
+        if data.pressed {
+            data.hold_duration = now.saturating_sub(data.pressed_at);
+        }
+    }
+}
+
+// Run condition for systems that react to a key, e.g. `jump_system.run_if(action_just_pressed("jump"))`.
+pub fn action_just_pressed(action: impl Into<String>) -> impl Fn(Res<ActionState>) -> bool + Clone {
+    let action = action.into();
+    move |action_state: Res<ActionState>| action_state.just_pressed(&action)
+}
+
+pub fn action_pressed(action: impl Into<String>) -> impl Fn(Res<ActionState>) -> bool + Clone {
+    let action = action.into();
+    move |action_state: Res<ActionState>| action_state.pressed(&action)
+}
+
+pub fn action_just_released(action: impl Into<String>) -> impl Fn(Res<ActionState>) -> bool + Clone {
+    let action = action.into();
+    move |action_state: Res<ActionState>| action_state.just_released(&action)
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ui::input::input_map::{InputMaps, KeyStates};
+use crate::ui::input::input_map::action_state::ActionState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisSettings {
+    // Analog input below this magnitude reads as zero, the rest is rescaled to start from zero.
+    pub dead_zone: f32,
+    pub sensitivity: f32,
+    pub inverted: bool,
+}
+
+impl AxisSettings {
+    // apply_dead_zone rescales by what is left above the dead zone, so it has to stay below 1.
+    pub fn has_valid_dead_zone(&self) -> bool {
+        (0.0..1.0).contains(&self.dead_zone)
+    }
+
+    // Falls back to the default dead zone when the current one can't be used.
+    pub fn validate(&mut self, key: &str) {
+        if !self.has_valid_dead_zone() {
+            warn!(key, dead_zone = self.dead_zone, "Invalid axis dead zone, using the default");
+            self.dead_zone = Self::default().dead_zone;
+        }
+    }
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            sensitivity: 1.0,
+            inverted: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisSource {
+    // Each held key counts as a full push in its direction.
+    Keys { negative: KeyCode, positive: KeyCode },
+    // Uses the analog value of the buttons, so triggers work as well as digital buttons.
+    GamepadButtons { negative: GamepadButton, positive: GamepadButton },
+    GamepadAxis(GamepadAxis),
+    // Wheel steps scrolled this frame, not limited to -1..1.
+    MouseWheelX,
+    MouseWheelY,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DualAxisSource {
+    Keys { up: KeyCode, down: KeyCode, left: KeyCode, right: KeyCode },
+    GamepadStick { x: GamepadAxis, y: GamepadAxis },
+}
+
+// An analog action like zoom. The sources are added together.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub sources: Vec<AxisSource>,
+    #[serde(default)]
+    pub settings: AxisSettings,
+}
+
+// A 2D analog action like move. The dead zone is radial and inversion applies to y.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DualAxisBinding {
+    pub sources: Vec<DualAxisSource>,
+    #[serde(default)]
+    pub settings: AxisSettings,
+}
+
+// Raw device values for one frame.
+pub struct AnalogInput<'a> {
+    pub key_states: &'a KeyStates,
+    pub gamepads: Vec<&'a Gamepad>,
+    pub wheel: Vec2,
+}
+
+impl AnalogInput<'_> {
+    fn key_axis(&self, negative: KeyCode, positive: KeyCode) -> f32 {
+        let mut value = 0.0;
+        if self.key_states.is_pressed(negative) {
+            value -= 1.0;
+        }
+        if self.key_states.is_pressed(positive) {
+            value += 1.0;
+        }
+        value
+    }
+
+    // The gamepad pushed furthest wins, so an idle second gamepad doesn't cancel the first.
+    fn gamepad_value(&self, value: impl Fn(&Gamepad) -> f32) -> f32 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| value(gamepad))
+            .fold(0.0, |strongest, value| if value.abs() > strongest.abs() { value } else { strongest })
+    }
+
+    fn gamepad_stick(&self, x: GamepadAxis, y: GamepadAxis) -> Vec2 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| Vec2::new(gamepad.get(x).unwrap_or(0.0), gamepad.get(y).unwrap_or(0.0)))
+            .fold(Vec2::ZERO, |strongest, value| if value.length_squared() > strongest.length_squared() { value } else { strongest })
+    }
+}
+
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= dead_zone {
+        return 0.0;
+    }
+    value.signum() * ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
+
+impl AxisBinding {
+    pub fn new(sources: Vec<AxisSource>) -> Self {
+        Self { sources, ..default() }
+    }
+
+    pub fn value(&self, input: &AnalogInput) -> f32 {
+        let mut bounded = 0.0;
+        let mut wheel = 0.0;
+        for source in self.sources.iter() {
+            match source {
+                AxisSource::Keys { negative, positive } => bounded += input.key_axis(*negative, *positive),
+                AxisSource::GamepadButtons { negative, positive } => {
+                    let value = input.gamepad_value(|gamepad| {
+                        gamepad.get(*positive).unwrap_or(0.0) - gamepad.get(*negative).unwrap_or(0.0)
+                    });
+                    bounded += apply_dead_zone(value, self.settings.dead_zone);
+                }
+                AxisSource::GamepadAxis(axis) => {
+                    let value = input.gamepad_value(|gamepad| gamepad.get(*axis).unwrap_or(0.0));
+                    bounded += apply_dead_zone(value, self.settings.dead_zone);
+                }
+                AxisSource::MouseWheelX => wheel += input.wheel.x,
+                AxisSource::MouseWheelY => wheel += input.wheel.y,
+            }
+        }
+
+        let value = (bounded.clamp(-1.0, 1.0) + wheel) * self.settings.sensitivity;
+        if self.settings.inverted { -value } else { value }
+    }
+}
+
+impl DualAxisBinding {
+    pub fn new(sources: Vec<DualAxisSource>) -> Self {
+        Self { sources, ..default() }
+    }
+
+    pub fn value(&self, input: &AnalogInput) -> Vec2 {
+        let mut value = Vec2::ZERO;
+        for source in self.sources.iter() {
+            match source {
+                DualAxisSource::Keys { up, down, left, right } => {
+                    value += Vec2::new(input.key_axis(*left, *right), input.key_axis(*down, *up));
+                }
+                DualAxisSource::GamepadStick { x, y } => {
+                    let stick = input.gamepad_stick(*x, *y);
+                    let length = apply_dead_zone(stick.length(), self.settings.dead_zone);
+                    value += stick.normalize_or_zero() * length;
+                }
+            }
+        }
+
+        // Diagonals are no faster than straight lines.
+        let mut value = value.clamp_length_max(1.0) * self.settings.sensitivity;
+        if self.settings.inverted {
+            value.y = -value.y;
+        }
+        value
+    }
+}
+
+pub fn default_axes() -> HashMap<String, AxisBinding> {
+    HashMap::from([(
+        "zoom".to_string(),
+        AxisBinding::new(vec![
+            AxisSource::MouseWheelY,
+            AxisSource::GamepadButtons {
+                negative: GamepadButton::LeftTrigger2,
+                positive: GamepadButton::RightTrigger2,
+            },
+        ]),
+    )])
+}
+
+pub fn default_dual_axes() -> HashMap<String, DualAxisBinding> {
+    HashMap::from([(
+        "move".to_string(),
+        DualAxisBinding::new(vec![
+            DualAxisSource::Keys {
+                up: KeyCode::KeyW,
+                down: KeyCode::KeyS,
+                left: KeyCode::KeyA,
+                right: KeyCode::KeyD,
+            },
+            DualAxisSource::Keys {
+                up: KeyCode::ArrowUp,
+                down: KeyCode::ArrowDown,
+                left: KeyCode::ArrowLeft,
+                right: KeyCode::ArrowRight,
+            },
+            DualAxisSource::GamepadStick {
+                x: GamepadAxis::LeftStickX,
+                y: GamepadAxis::LeftStickY,
+            },
+        ]),
+    )])
+}
+
+pub fn analog_action_state_system(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut action_state: ResMut<ActionState>,
+    gamepads: Query<&Gamepad>,
+    key_states: Res<KeyStates>,
+    input_maps: Res<InputMaps>) {
+
+    let mut wheel = Vec2::ZERO;
+    for event in wheel_events.read() {
+        let scale = match event.unit {
+            MouseScrollUnit::Line => 1.0,
+            MouseScrollUnit::Pixel => 1.0 / input_maps.wheel_pixels_per_step(),
+        };
+        wheel += Vec2::new(event.x, event.y) * scale;
+    }
+
+    let input = AnalogInput {
+        key_states: &key_states,
+        gamepads: gamepads.iter().collect(),
+        wheel,
+    };
+
+    let axes = input_maps
+        .active_axes()
+        .into_iter()
+        .map(|(name, binding)| (name.clone(), binding.value(&input)))
+        .collect();
+    let dual_axes = input_maps
+        .active_dual_axes()
+        .into_iter()
+        .map(|(name, binding)| (name.clone(), binding.value(&input)))
+        .collect();
+
+    action_state.set_analog(axes, dual_axes);
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+use bevy::asset::ron;
+use bevy::input::keyboard::Key;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ui::input::input_map::analog::{AxisBinding, DualAxisBinding};
+use crate::ui::input::input_map::gestures::TouchGesture;
+use crate::ui::input::input_map::key_repeat::KeyRepeat;
+use crate::ui::input::input_map::{AxisDirection, InputBindings, InputChord, InputContext, InputMaps, InputSequence, InputValue, Modifiers, WheelDirection};
+
+// Where the player's bindings are stored. Relative paths are resolved from the working directory.
+#[derive(Resource, Clone, Debug)]
+pub struct InputBindingsPath(pub PathBuf);
+
+impl Default for InputBindingsPath {
+    fn default() -> Self {
+        Self(PathBuf::from("input_bindings.ron"))
+    }
+}
+
+// Human editable form of the bindings. Every binding matches on press.
+//
+// (
+//     contexts: {
+//         "menu": (
+//             actions: {
+//                 "ui_confirm": (
+//                     bindings: [
+//                         (value: Keyboard(key_code: Enter, logical_key: Enter)),
+//                         (value: GamepadButton(South)),
+//                     ],
+//                 ),
+//             },
+//         ),
+//         "gameplay": (
+//             actions: {
+//                 "save": (
+//                     bindings: [(modifiers: (control: true), value: Keyboard(key_code: KeyS, logical_key: Character("s")))],
+//                 ),
+//                 "undo": (
+//                     bindings: [(modifiers: (control: true), value: LogicalKey(Character("z")))],
+//                 ),
+//             },
+//             axes: {
+//                 "zoom": (sources: [MouseWheelY], settings: (sensitivity: 2.0)),
+//             },
+//         ),
+//     },
+// )
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputBindingsFile {
+    #[serde(default)]
+    pub contexts: BTreeMap<String, ContextBindings>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ContextBindings {
+    #[serde(default)]
+    pub actions: BTreeMap<String, ActionBindings>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub axes: BTreeMap<String, AxisBinding>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dual_axes: BTreeMap<String, DualAxisBinding>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ActionBindings {
+    #[serde(default)]
+    pub bindings: Vec<BindingChord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<BindingSequence>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<BindingRepeat>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingChord {
+    #[serde(default, skip_serializing_if = "is_no_modifiers")]
+    pub modifiers: Modifiers,
+    pub value: BindingValue,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingSequence {
+    pub steps: Vec<BindingChord>,
+    pub timeout_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingRepeat {
+    pub initial_delay_ms: u64,
+    pub interval_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BindingValue {
+    // Matches the physical key, logical_key is only used to name it.
+    Keyboard { key_code: KeyCode, logical_key: Key },
+    // Matches whatever physical key produces this key on the active layout.
+    LogicalKey(Key),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis, AxisDirection),
+    MouseButton(MouseButton),
+    MouseWheel(WheelDirection),
+    Gesture(TouchGesture),
+}
+
+fn is_no_modifiers(modifiers: &Modifiers) -> bool {
+    *modifiers == Modifiers::NONE
+}
+
+impl From<BindingValue> for InputValue {
+    fn from(value: BindingValue) -> Self {
+        match value {
+            BindingValue::Keyboard { key_code, logical_key } => InputValue::key_pressed(key_code, logical_key),
+            BindingValue::LogicalKey(key) => InputValue::logical_key_pressed(key),
+            BindingValue::GamepadButton(button) => InputValue::gamepad_button_pressed(button),
+            BindingValue::GamepadAxis(axis, direction) => InputValue::gamepad_axis_pressed(axis, direction),
+            BindingValue::MouseButton(button) => InputValue::mouse_button_pressed(button),
+            BindingValue::MouseWheel(direction) => InputValue::MouseWheel(direction),
+            BindingValue::Gesture(gesture) => InputValue::Gesture(gesture),
+        }
+    }
+}
+
+impl BindingValue {
+    // Values that don't match on press (e.g. released keys) can't be stored.
+    pub fn from_input_value(input_value: &InputValue) -> Option<Self> {
+        if !input_value.is_pressed() {
+            return None;
+        }
+
+        Some(match input_value {
+            InputValue::Keyboard(keyboard_input) => BindingValue::Keyboard {
+                key_code: keyboard_input.key_code,
+                logical_key: keyboard_input.logical_key.clone(),
+            },
+            InputValue::LogicalKey { key, .. } => BindingValue::LogicalKey(key.clone()),
+            InputValue::GamepadButton { button, .. } => BindingValue::GamepadButton(*button),
+            InputValue::GamepadAxis { axis, direction, .. } => BindingValue::GamepadAxis(*axis, *direction),
+            InputValue::MouseButton { button, .. } => BindingValue::MouseButton(*button),
+            InputValue::MouseWheel(direction) => BindingValue::MouseWheel(*direction),
+            InputValue::Gesture(gesture) => BindingValue::Gesture(*gesture),
+        })
+    }
+}
+
+impl From<BindingChord> for InputChord {
+    fn from(chord: BindingChord) -> Self {
+        InputChord::new(chord.modifiers, chord.value.into())
+    }
+}
+
+impl BindingChord {
+    pub fn from_chord(chord: &InputChord) -> Option<Self> {
+        Some(Self {
+            modifiers: chord.modifiers,
+            value: BindingValue::from_input_value(&chord.value)?,
+        })
+    }
+}
+
+impl From<ActionBindings> for InputBindings {
+    fn from(action_bindings: ActionBindings) -> Self {
+        let mut bindings = InputBindings::default();
+        for chord in action_bindings.bindings {
+            bindings.insert_chord(chord.into());
+        }
+        bindings.repeat = action_bindings.repeat.map(|repeat| KeyRepeat::new(
+            Duration::from_millis(repeat.initial_delay_ms),
+            Duration::from_millis(repeat.interval_ms),
+        ));
+        for sequence in action_bindings.sequences {
+            bindings.sequences.insert(InputSequence {
+                steps: sequence.steps.into_iter().map(InputChord::from).collect(),
+                timeout: Duration::from_millis(sequence.timeout_ms),
+            });
+        }
+        bindings
+    }
+}
+
+impl ActionBindings {
+    pub fn from_input_bindings(input_bindings: &InputBindings) -> Self {
+        let mut bindings = input_bindings
+            .iter_chords()
+            .filter_map(|chord| BindingChord::from_chord(&chord))
+            .collect::<Vec<_>>();
+        // Hash set order is random, keep the file stable between saves.
+        bindings.sort_by_cached_key(|chord| format!("{:?}", chord));
+
+        let mut sequences = input_bindings
+            .sequences
+            .iter()
+            .filter_map(|sequence| {
+                Some(BindingSequence {
+                    steps: sequence.steps.iter().map(BindingChord::from_chord).collect::<Option<Vec<_>>>()?,
+                    timeout_ms: sequence.timeout.as_millis() as u64,
+                })
+            })
+            .collect::<Vec<_>>();
+        sequences.sort_by_cached_key(|sequence| format!("{:?}", sequence));
+
+        let repeat = input_bindings.repeat.map(|repeat| BindingRepeat {
+            initial_delay_ms: repeat.initial_delay.as_millis() as u64,
+            interval_ms: repeat.interval.as_millis() as u64,
+        });
+
+        Self { bindings, sequences, repeat }
+    }
+}
+
+impl InputBindingsFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+impl ContextBindings {
+    pub fn from_input_context(input_context: &InputContext) -> Self {
+        Self {
+            actions: input_context
+                .iter_bindings()
+                .map(|(key, bindings)| (key.clone(), ActionBindings::from_input_bindings(bindings)))
+                .collect(),
+            axes: input_context
+                .iter_axes()
+                .map(|(key, binding)| (key.clone(), binding.clone()))
+                .collect(),
+            dual_axes: input_context
+                .iter_dual_axes()
+                .map(|(key, binding)| (key.clone(), binding.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl InputMaps {
+    pub fn to_bindings_file(&self) -> InputBindingsFile {
+        InputBindingsFile {
+            contexts: self
+                .iter_contexts()
+                .map(|(name, input_context)| (name.clone(), ContextBindings::from_input_context(input_context)))
+                .collect(),
+        }
+    }
+
+    // Actions in the file replace their current bindings, other actions keep theirs.
+    pub fn merge_bindings_file(&mut self, bindings_file: InputBindingsFile) {
+        for (name, context_bindings) in bindings_file.contexts {
+            let input_context = self.context_or_insert(name);
+            for (key, action_bindings) in context_bindings.actions {
+                input_context.set_bindings(key, action_bindings.into());
+            }
+            for (key, mut binding) in context_bindings.axes {
+                binding.settings.validate(&key);
+                input_context.set_axis(key, binding);
+            }
+            for (key, mut binding) in context_bindings.dual_axes {
+                binding.settings.validate(&key);
+                input_context.set_dual_axis(key, binding);
+            }
+        }
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        self.to_bindings_file().save(path)
+    }
+
+    pub fn load_from_file(&mut self, path: &Path) -> Result<()> {
+        let bindings_file = InputBindingsFile::load(path)?;
+        self.merge_bindings_file(bindings_file);
+        Ok(())
+    }
+}
+
+pub fn load_input_bindings_system(mut input_maps: ResMut<InputMaps>, bindings_path: Res<InputBindingsPath>) {
+    let path = bindings_path.0.as_path();
+    if !path.exists() {
+        debug!(?path, "No saved input bindings, using defaults");
+        return;
+    }
+
+    match input_maps.load_from_file(path) {
+        Ok(()) => info!(?path, "Input bindings loaded"),
+        Err(e) => error!("Error: loading input bindings from {:?} failed: {}", path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::input::input_map::analog::AxisSettings;
+
+    fn loaded_dead_zone(dead_zone: &str) -> (f32, f32) {
+        let text = format!(r#"(
+            contexts: {{
+                "gameplay": (
+                    axes: {{ "zoom": (sources: [MouseWheelY], settings: (dead_zone: {0})) }},
+                    dual_axes: {{ "move": (sources: [GamepadStick(x: LeftStickX, y: LeftStickY)], settings: (dead_zone: {0})) }},
+                ),
+            }},
+        )"#, dead_zone);
+        let mut input_maps = InputMaps::default();
+        input_maps.merge_bindings_file(ron::from_str(&text).unwrap());
+        let context = input_maps.context("gameplay").unwrap();
+        (
+            context.get_axis("zoom").unwrap().settings.dead_zone,
+            context.get_dual_axis("move").unwrap().settings.dead_zone,
+        )
+    }
+
+    #[test]
+    fn dead_zones_outside_zero_to_one_fall_back_to_the_default() {
+        let default = AxisSettings::default().dead_zone;
+        assert_eq!(loaded_dead_zone("0.0"), (0.0, 0.0));
+        assert_eq!(loaded_dead_zone("0.99"), (0.99, 0.99));
+        assert_eq!(loaded_dead_zone("1.0"), (default, default));
+        assert_eq!(loaded_dead_zone("-0.1"), (default, default));
+        assert_eq!(loaded_dead_zone("NaN"), (default, default));
+    }
+}
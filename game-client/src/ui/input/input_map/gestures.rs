@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use bevy::input::touch::{TouchInput, TouchPhase};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ui::input::input_map::{InputMaps, InputValue, KeyStates, MappedInputEvent, SequenceStates};
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// Gestures have no released state, like wheel steps.
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TouchGesture {
+    Tap,
+    // The second tap of a double tap replaces its Tap.
+    DoubleTap,
+    // Fired while the finger is still down. Lifting it afterwards produces nothing.
+    LongPress,
+    Swipe(SwipeDirection),
+}
+
+// Distances are in logical pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GestureSettings {
+    // How far a touch may wander and still count as a tap or long press.
+    pub tap_max_distance: f32,
+    pub double_tap_interval: Duration,
+    pub long_press_duration: Duration,
+    pub swipe_min_distance: f32,
+    pub swipe_max_duration: Duration,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            tap_max_distance: 20.0,
+            double_tap_interval: Duration::from_millis(300),
+            long_press_duration: Duration::from_millis(500),
+            swipe_min_distance: 80.0,
+            swipe_max_duration: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ActiveTouch {
+    start_position: Vec2,
+    position: Vec2,
+    start_time: Duration,
+    long_pressed: bool,
+}
+
+// Turns touch events into gestures. Each finger is recognised on its own.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct GestureRecognizer {
+    touches: HashMap<u64, ActiveTouch>,
+    // Position and time of the last tap, for double taps.
+    last_tap: Option<(Vec2, Duration)>,
+}
+
+impl GestureRecognizer {
+    pub fn touch_input(&mut self, input: &TouchInput, now: Duration, settings: &GestureSettings) -> Option<TouchGesture> {
+        match input.phase {
+            TouchPhase::Started => {
+                self.touches.insert(input.id, ActiveTouch {
+                    start_position: input.position,
+                    position: input.position,
+                    start_time: now,
+                    long_pressed: false,
+                });
+                None
+            }
+            TouchPhase::Moved => {
+                if let Some(touch) = self.touches.get_mut(&input.id) {
+                    touch.position = input.position;
+                }
+                None
+            }
+            TouchPhase::Canceled => {
+                self.touches.remove(&input.id);
+                None
+            }
+            TouchPhase::Ended => {
+                let mut touch = self.touches.remove(&input.id)?;
+                touch.position = input.position;
+                self.touch_ended(touch, now, settings)
+            }
+        }
+    }
+
+    // Long presses don't need a touch event, call this every frame.
+    pub fn update(&mut self, now: Duration, settings: &GestureSettings) -> Vec<TouchGesture> {
+        let mut gestures = Vec::new();
+        for touch in self.touches.values_mut() {
+            if !touch.long_pressed
+                && now.saturating_sub(touch.start_time) >= settings.long_press_duration
+                && touch.position.distance(touch.start_position) <= settings.tap_max_distance {
+                touch.long_pressed = true;
+                gestures.push(TouchGesture::LongPress);
+            }
+        }
+        gestures
+    }
+
+    fn touch_ended(&mut self, touch: ActiveTouch, now: Duration, settings: &GestureSettings) -> Option<TouchGesture> {
+        if touch.long_pressed {
+            return None;
+        }
+
+        let delta = touch.position - touch.start_position;
+        let duration = now.saturating_sub(touch.start_time);
+
+        if delta.length() >= settings.swipe_min_distance {
+            if duration > settings.swipe_max_duration {
+                return None;
+            }
+            // Window coordinates grow downwards.
+            let direction = if delta.x.abs() >= delta.y.abs() {
+                if delta.x > 0.0 { SwipeDirection::Right } else { SwipeDirection::Left }
+            } else if delta.y > 0.0 {
+                SwipeDirection::Down
+            } else {
+                SwipeDirection::Up
+            };
+            return Some(TouchGesture::Swipe(direction));
+        }
+
+        if delta.length() > settings.tap_max_distance || duration >= settings.long_press_duration {
+            return None;
+        }
+
+        let is_double_tap = self.last_tap.is_some_and(|(last_position, last_time)| {
+            now.saturating_sub(last_time) <= settings.double_tap_interval
+                && touch.position.distance(last_position) <= settings.tap_max_distance
+        });
+        if is_double_tap {
+            self.last_tap = None;
+            return Some(TouchGesture::DoubleTap);
+        }
+
+        self.last_tap = Some((touch.position, now));
+        Some(TouchGesture::Tap)
+    }
+}
+
+pub fn managed_touch_input_system(
+    mut touch_events: EventReader<TouchInput>,
+    mut recognizer: ResMut<GestureRecognizer>,
+    mut sequence_states: ResMut<SequenceStates>,
+    mut mapped_event_writer: EventWriter<MappedInputEvent>,
+    key_states: Res<KeyStates>,
+    time: Res<Time>,
+    input_maps: Res<InputMaps>) {
+
+    let now = time.elapsed();
+    let settings = &input_maps.gesture_settings;
+    let mut gestures = touch_events
+        .read()
+        .filter_map(|event| recognizer.touch_input(event, now, settings))
+        .collect::<Vec<_>>();
+    gestures.extend(recognizer.update(now, settings));
+
+    for gesture in gestures {
+        debug!(?gesture, "Touch gesture");
+        let input_value = InputValue::Gesture(gesture);
+        mapped_event_writer.send(MappedInputEvent {
+            keys: input_maps.map_input(&input_value, key_states.modifiers(), &mut sequence_states, now),
+            input_value: Some(input_value),
+            modifiers: key_states.modifiers(),
+            ..default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn touch(phase: TouchPhase, id: u64, x: f32, y: f32) -> TouchInput {
+        TouchInput {
+            phase,
+            position: Vec2::new(x, y),
+            window: Entity::PLACEHOLDER,
+            force: None,
+            id,
+        }
+    }
+
+    // A single finger going down at (0, 0) and lifting at (x, y).
+    fn stroke(recognizer: &mut GestureRecognizer, start: Duration, end: Duration, x: f32, y: f32) -> Option<TouchGesture> {
+        let settings = GestureSettings::default();
+        assert_eq!(recognizer.touch_input(&touch(TouchPhase::Started, 0, 0.0, 0.0), start, &settings), None);
+        assert_eq!(recognizer.touch_input(&touch(TouchPhase::Moved, 0, x / 2.0, y / 2.0), start, &settings), None);
+        recognizer.touch_input(&touch(TouchPhase::Ended, 0, x, y), end, &settings)
+    }
+
+    #[test]
+    fn tap_and_double_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(100), 0.0, 0.0), Some(TouchGesture::Tap));
+        assert_eq!(stroke(&mut recognizer, ms(300), ms(400), 5.0, 0.0), Some(TouchGesture::DoubleTap));
+        // The double tap used up the first tap.
+        assert_eq!(stroke(&mut recognizer, ms(500), ms(600), 0.0, 0.0), Some(TouchGesture::Tap));
+        // Too late for a double tap.
+        assert_eq!(stroke(&mut recognizer, ms(901), ms(950), 0.0, 0.0), Some(TouchGesture::Tap));
+    }
+
+    #[test]
+    fn tap_distance_boundary() {
+        let settings = GestureSettings::default();
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(100), settings.tap_max_distance, 0.0), Some(TouchGesture::Tap));
+        let mut recognizer = GestureRecognizer::default();
+        // Neither a tap nor far enough for a swipe.
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(100), settings.tap_max_distance + 0.5, 0.0), None);
+    }
+
+    #[test]
+    fn swipe_directions() {
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(200), 100.0, 10.0), Some(TouchGesture::Swipe(SwipeDirection::Right)));
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(200), -100.0, 10.0), Some(TouchGesture::Swipe(SwipeDirection::Left)));
+        // Window coordinates grow downwards.
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(200), 10.0, 100.0), Some(TouchGesture::Swipe(SwipeDirection::Down)));
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(200), 10.0, -100.0), Some(TouchGesture::Swipe(SwipeDirection::Up)));
+    }
+
+    #[test]
+    fn swipe_distance_and_duration_boundaries() {
+        let settings = GestureSettings::default();
+        let mut recognizer = GestureRecognizer::default();
+        let distance = settings.swipe_min_distance;
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(100), distance, 0.0), Some(TouchGesture::Swipe(SwipeDirection::Right)));
+        assert_eq!(stroke(&mut recognizer, ms(0), ms(100), distance - 0.5, 0.0), None);
+        assert_eq!(stroke(&mut recognizer, ms(0), settings.swipe_max_duration, distance, 0.0), Some(TouchGesture::Swipe(SwipeDirection::Right)));
+        assert_eq!(stroke(&mut recognizer, ms(0), settings.swipe_max_duration + ms(1), distance, 0.0), None);
+    }
+
+    #[test]
+    fn long_press_boundary() {
+        let settings = GestureSettings::default();
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.touch_input(&touch(TouchPhase::Started, 0, 0.0, 0.0), ms(0), &settings);
+        assert!(recognizer.update(settings.long_press_duration - ms(1), &settings).is_empty());
+        assert_eq!(recognizer.update(settings.long_press_duration, &settings), vec![TouchGesture::LongPress]);
+        // Only once, and lifting the finger produces nothing.
+        assert!(recognizer.update(settings.long_press_duration + ms(100), &settings).is_empty());
+        assert_eq!(recognizer.touch_input(&touch(TouchPhase::Ended, 0, 0.0, 0.0), ms(700), &settings), None);
+    }
+
+    #[test]
+    fn fingers_are_recognised_on_their_own_and_cancel_drops_them() {
+        let settings = GestureSettings::default();
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.touch_input(&touch(TouchPhase::Started, 1, 0.0, 0.0), ms(0), &settings);
+        recognizer.touch_input(&touch(TouchPhase::Started, 2, 500.0, 500.0), ms(0), &settings);
+        recognizer.touch_input(&touch(TouchPhase::Canceled, 2, 500.0, 500.0), ms(50), &settings);
+        assert_eq!(recognizer.touch_input(&touch(TouchPhase::Ended, 2, 500.0, 500.0), ms(100), &settings), None);
+        assert_eq!(recognizer.touch_input(&touch(TouchPhase::Ended, 1, 100.0, 0.0), ms(100), &settings), Some(TouchGesture::Swipe(SwipeDirection::Right)));
+        assert!(recognizer.update(ms(1000), &settings).is_empty());
+    }
+}
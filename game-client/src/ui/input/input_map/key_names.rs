@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use bevy::input::keyboard::Key;
+use bevy::prelude::*;
+use crate::ui::input::input_map::{AxisDirection, InputChord, InputValue, Modifiers, WheelDirection};
+use crate::ui::input::input_map::gestures::{SwipeDirection, TouchGesture};
+
+// The logical key each physical key produced on its last plain press. Winit can't be asked for the
+// layout, so it is learned as the player types, and bindings are named after what their keys print.
+#[derive(Resource, Default)]
+pub struct KeyboardLayout {
+    keys: HashMap<KeyCode, Key>,
+}
+
+impl KeyboardLayout {
+    pub fn logical_key(&self, key_code: KeyCode) -> Option<&Key> {
+        self.keys.get(&key_code)
+    }
+
+    // Modifiers change the produced key (Shift+1 is '!'), so only presses made without any should be
+    // learned. The managed keyboard system knows the modifiers each event was pressed with.
+    pub(crate) fn learn(&mut self, key_code: KeyCode, logical_key: Key) {
+        self.keys.insert(key_code, logical_key);
+    }
+}
+
+pub fn key_display_name(key: &Key) -> String {
+    match key {
+        Key::Character(character) => character.to_uppercase(),
+        Key::Space => "Space".into(),
+        Key::Escape => "Esc".into(),
+        Key::Enter => "Enter".into(),
+        Key::Tab => "Tab".into(),
+        Key::Backspace => "Backspace".into(),
+        Key::Delete => "Del".into(),
+        Key::ArrowUp => "↑".into(),
+        Key::ArrowDown => "↓".into(),
+        Key::ArrowLeft => "←".into(),
+        Key::ArrowRight => "→".into(),
+        Key::PageUp => "PgUp".into(),
+        Key::PageDown => "PgDn".into(),
+        _ => format!("{:?}", key),
+    }
+}
+
+// Name of the key in the US layout, for keys the layout hasn't been learned for.
+pub fn key_code_display_name(key_code: KeyCode) -> String {
+    let name = format!("{:?}", key_code);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+impl InputValue {
+    pub fn display_name(&self, keyboard_layout: &KeyboardLayout) -> String {
+        match self {
+            InputValue::Keyboard(keyboard_input) => {
+                let logical_key = keyboard_layout
+                    .logical_key(keyboard_input.key_code)
+                    .or(Some(&keyboard_input.logical_key))
+                    .filter(|key| !matches!(key, Key::Unidentified(_) | Key::Dead(_)));
+                match logical_key {
+                    Some(key) => key_display_name(key),
+                    None => key_code_display_name(keyboard_input.key_code),
+                }
+            }
+            InputValue::LogicalKey { key, .. } => key_display_name(key),
+            InputValue::GamepadButton { button, .. } => format!("{:?}", button),
+            InputValue::GamepadAxis { axis, direction, .. } => {
+                let sign = match direction {
+                    AxisDirection::Positive => "+",
+                    AxisDirection::Negative => "-",
+                };
+                format!("{:?}{}", axis, sign)
+            }
+            InputValue::MouseButton { button, .. } => format!("Mouse {:?}", button),
+            InputValue::MouseWheel(direction) => match direction {
+                WheelDirection::Up => "Wheel Up".into(),
+                WheelDirection::Down => "Wheel Down".into(),
+                WheelDirection::Left => "Wheel Left".into(),
+                WheelDirection::Right => "Wheel Right".into(),
+            },
+            InputValue::Gesture(gesture) => match gesture {
+                TouchGesture::Tap => "Tap".into(),
+                TouchGesture::DoubleTap => "Double Tap".into(),
+                TouchGesture::LongPress => "Long Press".into(),
+                TouchGesture::Swipe(SwipeDirection::Up) => "Swipe Up".into(),
+                TouchGesture::Swipe(SwipeDirection::Down) => "Swipe Down".into(),
+                TouchGesture::Swipe(SwipeDirection::Left) => "Swipe Left".into(),
+                TouchGesture::Swipe(SwipeDirection::Right) => "Swipe Right".into(),
+            },
+        }
+    }
+}
+
+impl Modifiers {
+    // E.g. "Ctrl+Shift+", empty without modifiers.
+    pub fn display_prefix(&self) -> String {
+        let mut prefix = String::new();
+        for (held, modifier) in [
+            (self.control, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+            (self.super_key, "Super+"),
+        ] {
+            if held {
+                prefix.push_str(modifier);
+            }
+        }
+        prefix
+    }
+}
+
+impl InputChord {
+    // E.g. "Ctrl+Shift+Z".
+    pub fn display_name(&self, keyboard_layout: &KeyboardLayout) -> String {
+        format!("{}{}", self.modifiers.display_prefix(), self.value.display_name(keyboard_layout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::ButtonState;
+    use bevy::input::keyboard::KeyboardInput;
+    use super::*;
+    use crate::ui::input::input_map::{managed_keyboard_input_system, InputMaps, KeyStates, MappedInputEvent, SequenceStates};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_event::<KeyboardInput>();
+        app.add_event::<MappedInputEvent>();
+        app.init_resource::<Time>();
+        app.init_resource::<KeyStates>();
+        app.init_resource::<SequenceStates>();
+        app.init_resource::<KeyboardLayout>();
+        app.insert_resource(InputMaps::default());
+        app.add_systems(Update, managed_keyboard_input_system);
+        app
+    }
+
+    fn send(app: &mut App, key_code: KeyCode, logical_key: Key, state: ButtonState) {
+        app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key,
+            state,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    fn learned(app: &App, key_code: KeyCode) -> Option<Key> {
+        app.world().resource::<KeyboardLayout>().logical_key(key_code).cloned()
+    }
+
+    #[test]
+    fn shifted_press_is_not_learned_when_shift_is_released_in_the_same_update() {
+        let mut app = app();
+        send(&mut app, KeyCode::ShiftLeft, Key::Shift, ButtonState::Pressed);
+        send(&mut app, KeyCode::Digit1, Key::Character("!".into()), ButtonState::Pressed);
+        send(&mut app, KeyCode::ShiftLeft, Key::Shift, ButtonState::Released);
+        app.update();
+        assert_eq!(learned(&app, KeyCode::Digit1), None);
+
+        send(&mut app, KeyCode::Digit1, Key::Character("!".into()), ButtonState::Released);
+        send(&mut app, KeyCode::Digit1, Key::Character("1".into()), ButtonState::Pressed);
+        app.update();
+        assert_eq!(learned(&app, KeyCode::Digit1), Some(Key::Character("1".into())));
+    }
+
+    #[test]
+    fn plain_press_is_learned_when_shift_is_pressed_in_the_same_update() {
+        let mut app = app();
+        send(&mut app, KeyCode::Digit1, Key::Character("1".into()), ButtonState::Pressed);
+        send(&mut app, KeyCode::ShiftLeft, Key::Shift, ButtonState::Pressed);
+        app.update();
+        assert_eq!(learned(&app, KeyCode::Digit1), Some(Key::Character("1".into())));
+        assert_eq!(learned(&app, KeyCode::ShiftLeft), None);
+    }
+}
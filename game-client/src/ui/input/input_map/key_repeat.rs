@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use bevy::ecs::event::EventCursor;
+use bevy::prelude::*;
+use crate::ui::input::input_map::{InputMaps, InputSource, InputValue, KeyStates, MappedInputEvent};
+
+// Repeat timing of a held binding. Used instead of OS key repeats, which only keyboards have and
+// whose timing differs between systems.
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRepeat {
+    pub initial_delay: Duration,
+    // Time between repeats once they started.
+    pub interval: Duration,
+}
+
+impl KeyRepeat {
+    pub const NAVIGATION: Self = Self {
+        initial_delay: Duration::from_millis(400),
+        interval: Duration::from_millis(80),
+    };
+
+    pub fn new(initial_delay: Duration, interval: Duration) -> Self {
+        Self { initial_delay, interval }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RepeatingKey {
+    repeat: KeyRepeat,
+    next_repeat: Duration,
+}
+
+#[derive(Clone, Debug)]
+struct HeldInput {
+    input_value: InputValue,
+    keys: HashMap<String, RepeatingKey>,
+}
+
+// Inputs that are held down and mapped to keys with a KeyRepeat.
+#[derive(Resource, Default)]
+pub struct RepeatStates {
+    held: HashMap<(Option<Entity>, InputSource), HeldInput>,
+}
+
+impl RepeatStates {
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+// Sends a repeat MappedInputEvent for the keys of every held input that are due. The keys are mapped
+// again first with the modifiers held now, so keys of contexts that were popped in the meantime and
+// chords whose modifiers were released stop repeating. Timed with virtual
+// time, so a replayed recording repeats exactly as it did when it was recorded.
+pub fn key_repeat_system(
+    mut mapped_events: ResMut<Events<MappedInputEvent>>,
+    mut cursor: Local<EventCursor<MappedInputEvent>>,
+    mut repeat_states: ResMut<RepeatStates>,
+    key_states: Res<KeyStates>,
+    time: Res<Time>,
+    input_maps: Res<InputMaps>) {
+
+    let now = time.elapsed();
+    let new_events = cursor.read(&mapped_events).cloned().collect::<Vec<_>>();
+
+    for event in new_events {
+        let Some(input_value) = event.input_value else {
+            continue;
+        };
+        if event.repeat {
+            continue;
+        }
+
+        let source = (event.gamepad, input_value.source());
+        if !input_value.is_pressed() {
+            repeat_states.held.remove(&source);
+            continue;
+        }
+
+        // Wheel steps and gestures are never held.
+        if let InputValue::MouseWheel(_) | InputValue::Gesture(_) = input_value {
+            continue;
+        }
+
+        let keys = event.keys
+            .iter()
+            .filter_map(|key| {
+                let repeat = input_maps.get_repeat(key)?;
+                Some((key.clone(), RepeatingKey {
+                    repeat,
+                    next_repeat: now + repeat.initial_delay,
+                }))
+            })
+            .collect::<HashMap<_, _>>();
+
+        if keys.is_empty() {
+            repeat_states.held.remove(&source);
+        } else {
+            repeat_states.held.insert(source, HeldInput {
+                input_value,
+                keys,
+            });
+        }
+    }
+
+    let modifiers = key_states.modifiers();
+    let mut repeats = Vec::new();
+    for ((gamepad, _), held) in repeat_states.held.iter_mut() {
+        let mapped = input_maps.get_mapped_keys(&held.input_value, modifiers);
+        let mut due = HashSet::new();
+        for (key, repeating) in held.keys.iter_mut() {
+            if now < repeating.next_repeat || !mapped.contains(key) {
+                continue;
+            }
+            // A long frame produces one repeat, not a burst of them.
+            repeating.next_repeat += repeating.repeat.interval;
+            if repeating.next_repeat <= now {
+                repeating.next_repeat = now + repeating.repeat.interval;
+            }
+            due.insert(key.clone());
+        }
+
+        if !due.is_empty() {
+            repeats.push(MappedInputEvent {
+                keys: due,
+                input_value: Some(held.input_value.clone()),
+                gamepad: *gamepad,
+                modifiers,
+                repeat: true,
+                ..default()
+            });
+        }
+    }
+
+    for repeat in repeats {
+        mapped_events.send(repeat);
+    }
+    // The repeats were sent by this system, don't read them back next frame.
+    cursor.read(&mapped_events).for_each(drop);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::ButtonState;
+    use bevy::input::keyboard::{Key, KeyboardInput};
+    use bevy::time::TimeUpdateStrategy;
+    use super::*;
+    use crate::ui::input::input_map::{managed_keyboard_input_system, SequenceStates};
+    use crate::ui::input::input_map::key_names::KeyboardLayout;
+
+    #[derive(Resource, Default)]
+    struct Repeated(Vec<HashSet<String>>);
+
+    fn record_repeats_system(mut events: EventReader<MappedInputEvent>, mut repeated: ResMut<Repeated>) {
+        repeated.0.extend(events.read().filter(|event| event.repeat).map(|event| event.keys.clone()));
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+        app.add_event::<KeyboardInput>();
+        app.add_event::<MappedInputEvent>();
+        app.init_resource::<KeyStates>();
+        app.init_resource::<SequenceStates>();
+        app.init_resource::<KeyboardLayout>();
+        app.init_resource::<RepeatStates>();
+        app.init_resource::<Repeated>();
+        app.insert_resource(InputMaps::default());
+        app.add_systems(Update, (managed_keyboard_input_system, key_repeat_system, record_repeats_system).chain());
+        app
+    }
+
+    fn send(app: &mut App, key_code: KeyCode, logical_key: Key, state: ButtonState) {
+        app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key,
+            state,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    fn take_repeats(app: &mut App) -> Vec<HashSet<String>> {
+        std::mem::take(&mut app.world_mut().resource_mut::<Repeated>().0)
+    }
+
+    #[test]
+    fn chord_stops_repeating_when_its_modifier_is_released() {
+        let mut app = app();
+        send(&mut app, KeyCode::ShiftLeft, Key::Shift, ButtonState::Pressed);
+        send(&mut app, KeyCode::Tab, Key::Tab, ButtonState::Pressed);
+        for _ in 0..8 {
+            app.update();
+        }
+        let repeats = take_repeats(&mut app);
+        assert!(!repeats.is_empty());
+        assert!(repeats.iter().all(|keys| keys.contains("ui_focus_previous")));
+
+        send(&mut app, KeyCode::ShiftLeft, Key::Shift, ButtonState::Released);
+        for _ in 0..8 {
+            app.update();
+        }
+        assert!(take_repeats(&mut app).iter().all(|keys| !keys.contains("ui_focus_previous")));
+    }
+}
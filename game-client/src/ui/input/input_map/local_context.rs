@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use crate::ui::input::focus::InputFocus;
+use crate::ui::input::input_map::{InputContext, InputMaps};
+
+// Copies the InputContext components of the focused entity and its ancestors into InputMaps, so every
+// mapping system sees them without walking the hierarchy itself. Only writes when the chain changed.
+pub fn local_input_context_system(
+    input_focus: Option<Res<InputFocus>>,
+    parents: Query<&Parent>,
+    contexts: Query<Ref<InputContext>>,
+    mut removed_contexts: RemovedComponents<InputContext>,
+    mut input_maps: ResMut<InputMaps>,
+) {
+    let focus = input_focus.and_then(|input_focus| input_focus.0);
+    let chain = focus
+        .into_iter()
+        .flat_map(|entity| std::iter::once(entity).chain(parents.iter_ancestors(entity)))
+        .filter(|entity| contexts.contains(*entity))
+        .collect::<Vec<_>>();
+
+    let removed = removed_contexts.read().count() > 0;
+    let same_entities = input_maps.local_contexts().map(|(entity, _)| entity).eq(chain.iter().copied());
+    let changed = chain.iter().any(|entity| contexts.get(*entity).is_ok_and(|context| context.is_changed()));
+    if same_entities && !changed && !removed {
+        return;
+    }
+
+    debug!(entities = ?chain, "Local input contexts changed");
+    let local_contexts = chain
+        .into_iter()
+        .filter_map(|entity| Some((entity, contexts.get(entity).ok()?.clone())))
+        .collect();
+    input_maps.set_local_contexts(local_contexts);
+}
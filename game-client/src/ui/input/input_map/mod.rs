@@ -1,9 +1,27 @@
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use bevy::ecs::system::SystemParam;
 use bevy::input::ButtonState;
-use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonStateChangedEvent};
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use maplit::hashset;
-use crate::event_system::HandledEventExt;
+use serde::{Deserialize, Serialize};
+use crate::event_system::{Capture, HandledEvent, UnhandledInputEvent};
+use crate::event_system::tracer::TraceEventAppExt;
+use crate::ui::input::input_map::analog::{AxisBinding, DualAxisBinding};
+use crate::ui::input::input_map::gestures::{GestureRecognizer, GestureSettings, SwipeDirection, TouchGesture};
+use crate::ui::input::input_map::key_names::KeyboardLayout;
+use crate::ui::input::input_map::key_repeat::{KeyRepeat, RepeatStates};
+
+pub mod bindings_file;
+pub mod action_state;
+pub mod analog;
+pub mod gestures;
+pub mod key_repeat;
+pub mod key_names;
+pub mod local_context;
 
 pub struct InputMapPlugin;
 
@@ -11,160 +29,1032 @@ impl Plugin for InputMapPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(InputMaps::default());
         app.insert_resource(KeyStates::default());
-        app.add_systems(PostUpdate, managed_keyboard_input_system);
+        app.insert_resource(AxisStates::default());
+        app.insert_resource(SequenceStates::default());
+        app.insert_resource(GestureRecognizer::default());
+        app.insert_resource(RepeatStates::default());
+        app.init_resource::<key_names::KeyboardLayout>();
+        app.init_resource::<bindings_file::InputBindingsPath>();
+        app.add_systems(PreStartup, bindings_file::load_input_bindings_system);
+        app.insert_resource(action_state::ActionState::default());
+        app.add_systems(
+            PostUpdate,
+            (
+                local_context::local_input_context_system,
+                (
+                    managed_keyboard_input_system,
+                    managed_gamepad_input_system,
+                    managed_mouse_input_system,
+                    gestures::managed_touch_input_system,
+                ),
+                key_repeat::key_repeat_system,
+                action_state::action_state_system,
+                analog::analog_action_state_system,
+            ).chain()
+        );
         // app.add_event::<ManagedKeyboardInput>();
         app.add_event::<MappedInputEvent>();
+        app.trace_event::<Capture<MappedInputEvent>>();
+        app.trace_event::<MappedInputEvent>();
+        app.trace_event::<UnhandledInputEvent<MappedInputEvent>>();
     }
 }
 
+pub const MENU_CONTEXT: &str = "menu";
+pub const TEXT_ENTRY_CONTEXT: &str = "text_entry";
+pub const GAMEPLAY_CONTEXT: &str = "gameplay";
+
 #[derive(Resource)]
 pub struct InputMaps {
-    map: HashMap<String, InputContext>,
-    pub(crate) comparison_cache: HashMap<InputValue, HashSet<String>>
+    contexts: HashMap<String, InputContext>,
+    // Stack of active context names, the last one is on top.
+    active_contexts: Vec<String>,
+    // Copies of the InputContext components along the focused entity's ancestors. They sit above the
+    // stack while the focus is inside their subtree.
+    local_contexts: Vec<(Entity, InputContext)>,
+    // How far a gamepad axis has to move before it counts as pressed in that direction.
+    pub axis_threshold: f32,
+    // Amount of pixel scrolling (touchpads etc.) that makes up one wheel step. Always positive, see
+    // set_wheel_pixels_per_step.
+    wheel_pixels_per_step: f32,
+    pub gesture_settings: GestureSettings,
 }
 
+// Everything bound to a single key.
+#[derive(Clone, Debug, Default)]
+pub struct InputBindings {
+    pub values: HashSet<InputValue>,
+    // Values that only match while the modifiers are held, e.g. Ctrl+S.
+    pub chords: HashSet<InputChord>,
+    pub sequences: HashSet<InputSequence>,
+    // Repeats the key while one of its bindings is held. None means the key fires once per press.
+    pub repeat: Option<KeyRepeat>,
+}
+
+// A named set of bindings (menu, gameplay, text entry...). Only contexts on the InputMaps stack produce keys.
+// As a component it gives a UI subtree its own bindings while the focus is inside it, see InputContext::local.
 #[derive(Clone, Debug, Default, Component)]
 pub struct InputContext {
-    pub values: HashSet<InputValue>,
+    map: HashMap<String, InputBindings>,
+    // Keys bound to each input value, grouped by the modifiers the binding requires.
+    pub(crate) comparison_cache: HashMap<InputValue, HashMap<Modifiers, HashSet<String>>>,
+    // Analog actions, read every frame instead of being mapped from input events.
+    axes: HashMap<String, AxisBinding>,
+    dual_axes: HashMap<String, DualAxisBinding>,
+    // Whether input this context doesn't map falls through to the context below it.
+    pub passthrough: bool,
+}
+
+impl InputBindings {
+    // Chords are compared the way they match, so Keyboard values only compare their key code.
+    pub fn contains_chord(&self, chord: &InputChord) -> bool {
+        self.iter_chords().any(|bound| bound.binding_key() == chord.binding_key())
+    }
+
+    // Chords without modifiers are kept in values.
+    pub fn insert_chord(&mut self, chord: InputChord) -> bool {
+        if chord.modifiers == Modifiers::NONE {
+            self.values.insert(chord.value)
+        } else {
+            self.chords.insert(chord)
+        }
+    }
+
+    pub fn remove_chord(&mut self, chord: &InputChord) -> bool {
+        let binding_key = chord.binding_key();
+        if chord.modifiers == Modifiers::NONE {
+            let count = self.values.len();
+            self.values.retain(|input_value| input_value.binding_key() != binding_key.value);
+            self.values.len() != count
+        } else {
+            let count = self.chords.len();
+            self.chords.retain(|bound| bound.binding_key() != binding_key);
+            self.chords.len() != count
+        }
+    }
+
+    pub fn iter_chords(&self) -> impl Iterator<Item = InputChord> + '_ {
+        self.values
+            .iter()
+            .map(|input_value| InputChord::new(Modifiers::NONE, input_value.clone()))
+            .chain(self.chords.iter().cloned())
+    }
+}
+
+#[derive(Hash, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub control: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub super_key: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self { control: false, shift: false, alt: false, super_key: false };
+    pub const CONTROL: Self = Self { control: true, ..Self::NONE };
+    pub const SHIFT: Self = Self { shift: true, ..Self::NONE };
+    pub const ALT: Self = Self { alt: true, ..Self::NONE };
+    pub const SUPER: Self = Self { super_key: true, ..Self::NONE };
+
+    pub fn with(self, other: Self) -> Self {
+        Self {
+            control: self.control || other.control,
+            shift: self.shift || other.shift,
+            alt: self.alt || other.alt,
+            super_key: self.super_key || other.super_key,
+        }
+    }
+
+    // Whether every modifier in other is also in self.
+    pub fn contains(&self, other: &Self) -> bool {
+        (self.control || !other.control)
+            && (self.shift || !other.shift)
+            && (self.alt || !other.alt)
+            && (self.super_key || !other.super_key)
+    }
+
+    pub fn count(&self) -> usize {
+        [self.control, self.shift, self.alt, self.super_key].into_iter().filter(|held| *held).count()
+    }
+}
+
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
+pub struct InputChord {
+    pub modifiers: Modifiers,
+    pub value: InputValue,
+}
+
+impl InputChord {
+    pub fn new(modifiers: Modifiers, value: InputValue) -> Self {
+        Self { modifiers, value }
+    }
+
+    pub fn matches(&self, input_value: &InputValue, modifiers: Modifiers) -> bool {
+        input_value.lookup_keys().contains(&self.value.binding_key()) && modifiers.contains(&self.modifiers)
+    }
+
+    pub fn binding_key(&self) -> Self {
+        Self::new(self.modifiers, self.value.binding_key())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindingConflict {
+    pub chord: InputChord,
+    pub keys: HashSet<String>,
+}
+
+impl std::fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} is already bound to {:?}", self.chord, self.keys)
+    }
+}
+
+impl std::error::Error for BindingConflict {}
+
+// Chords that have to be pressed one after another, each within timeout of the previous one.
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
+pub struct InputSequence {
+    pub steps: Vec<InputChord>,
+    pub timeout: Duration,
+}
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+impl AxisDirection {
+    pub fn from_value(value: f32, threshold: f32) -> Option<Self> {
+        if value >= threshold {
+            Some(AxisDirection::Positive)
+        } else if value <= -threshold {
+            Some(AxisDirection::Negative)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
+pub enum InputSource {
+    Key(KeyCode),
+    LogicalKey(Key),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis, AxisDirection),
+    MouseButton(MouseButton),
+    MouseWheel(WheelDirection),
+    Gesture(TouchGesture),
 }
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
 pub enum InputValue {
+    // Matches by physical key (KeyCode), so the binding stays in place on any layout. The logical key
+    // is only kept for showing the binding when the layout is unknown.
     Keyboard(KeyboardInput),
+    // Matches by the character or named key the layout produces, e.g. Ctrl+Z for undo on every layout.
+    // Characters match case-insensitively.
+    LogicalKey {
+        key: Key,
+        state: ButtonState,
+    },
+    GamepadButton {
+        button: GamepadButton,
+        state: ButtonState,
+    },
+    // An axis crossing InputMaps::axis_threshold in a direction is pressed, falling back below it is released.
+    GamepadAxis {
+        axis: GamepadAxis,
+        direction: AxisDirection,
+        state: ButtonState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ButtonState,
+    },
+    // A single wheel step. Wheels have no released state.
+    MouseWheel(WheelDirection),
+    // A recognised touch gesture, see GestureSettings for the thresholds.
+    Gesture(TouchGesture),
 }
 
 impl InputValue {
     pub fn matches_keycode(&self, other: &Self) -> bool {
         match (self, other) {
             (InputValue::Keyboard(a), InputValue::Keyboard(b)) => a.key_code == b.key_code,
+            _ => self == other,
+        }
+    }
+
+    pub fn key_pressed(key_code: KeyCode, logical_key: Key) -> Self {
+        InputValue::Keyboard(KeyboardInput {
+            key_code,
+            logical_key,
+            state: ButtonState::Pressed,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        })
+    }
+
+    pub fn logical_key_pressed(key: Key) -> Self {
+        InputValue::LogicalKey {
+            key,
+            state: ButtonState::Pressed,
+        }
+    }
+
+    // The part of a bound value that takes part in matching. Bindings are cached under this.
+    pub fn binding_key(&self) -> Self {
+        match self {
+            InputValue::Keyboard(keyboard_input) => InputValue::Keyboard(KeyboardInput {
+                logical_key: Key::Unidentified(NativeKey::Unidentified),
+                window: Entity::PLACEHOLDER,
+                ..keyboard_input.clone()
+            }),
+            InputValue::LogicalKey { key, state } => InputValue::LogicalKey {
+                key: normalize_logical_key(key),
+                state: *state,
+            },
+            _ => self.clone(),
+        }
+    }
+
+    // Binding keys an input event matches. A key press matches both physical and logical bindings.
+    pub fn lookup_keys(&self) -> Vec<Self> {
+        match self {
+            InputValue::Keyboard(keyboard_input) if !keyboard_input.repeat => vec![
+                self.binding_key(),
+                InputValue::LogicalKey {
+                    key: normalize_logical_key(&keyboard_input.logical_key),
+                    state: keyboard_input.state,
+                },
+            ],
+            _ => vec![self.binding_key()],
+        }
+    }
+
+    pub fn gamepad_button_pressed(button: GamepadButton) -> Self {
+        InputValue::GamepadButton {
+            button,
+            state: ButtonState::Pressed,
+        }
+    }
+
+    pub fn mouse_button_pressed(button: MouseButton) -> Self {
+        InputValue::MouseButton {
+            button,
+            state: ButtonState::Pressed,
+        }
+    }
+
+    pub fn gamepad_axis_pressed(axis: GamepadAxis, direction: AxisDirection) -> Self {
+        InputValue::GamepadAxis {
+            axis,
+            direction,
+            state: ButtonState::Pressed,
+        }
+    }
+
+    // The physical control behind the value, shared by its pressed and released states.
+    pub fn source(&self) -> InputSource {
+        match self {
+            InputValue::Keyboard(keyboard_input) => InputSource::Key(keyboard_input.key_code),
+            InputValue::LogicalKey { key, .. } => InputSource::LogicalKey(normalize_logical_key(key)),
+            InputValue::GamepadButton { button, .. } => InputSource::GamepadButton(*button),
+            InputValue::GamepadAxis { axis, direction, .. } => InputSource::GamepadAxis(*axis, *direction),
+            InputValue::MouseButton { button, .. } => InputSource::MouseButton(*button),
+            InputValue::MouseWheel(direction) => InputSource::MouseWheel(*direction),
+            InputValue::Gesture(gesture) => InputSource::Gesture(*gesture),
+        }
+    }
+
+    // Whether this is a fresh press. Only presses advance sequences.
+    pub fn is_pressed(&self) -> bool {
+        match self {
+            InputValue::Keyboard(keyboard_input) => keyboard_input.state.is_pressed() && !keyboard_input.repeat,
+            InputValue::LogicalKey { state, .. } => state.is_pressed(),
+            InputValue::GamepadButton { state, .. } => state.is_pressed(),
+            InputValue::GamepadAxis { state, .. } => state.is_pressed(),
+            InputValue::MouseButton { state, .. } => state.is_pressed(),
+            InputValue::MouseWheel(_) => true,
+            InputValue::Gesture(_) => true,
+        }
+    }
+
+    // Modifier keys only shape the chord of the key that follows them.
+    pub fn is_modifier(&self) -> bool {
+        match self {
+            InputValue::Keyboard(keyboard_input) => matches!(
+                keyboard_input.key_code,
+                KeyCode::ControlLeft | KeyCode::ControlRight
+                    | KeyCode::ShiftLeft | KeyCode::ShiftRight
+                    | KeyCode::AltLeft | KeyCode::AltRight
+                    | KeyCode::SuperLeft | KeyCode::SuperRight
+            ),
+            InputValue::LogicalKey { key, .. } => matches!(key, Key::Control | Key::Shift | Key::Alt | Key::Super),
+            _ => false,
         }
     }
 }
 
-impl InputMaps {
+impl InputContext {
+    // A context for an entity that adds to and overrides the global bindings, e.g. Home/End in a list.
+    // Input it doesn't map falls through to the next context.
+    pub fn local(map: HashMap<String, InputBindings>) -> Self {
+        Self::new(map, true)
+    }
 
-    fn add_input_cache(&mut self, key: String, input_value: InputValue) {
-        if let Some(cache) = self.comparison_cache.get_mut(&input_value) {
-            cache.insert(key);
-        } else {
-            let mut hash_set = HashSet::new();
-            hash_set.insert(key);
-            self.comparison_cache.insert(input_value, hash_set);
+    pub fn new(map: HashMap<String, InputBindings>, passthrough: bool) -> Self {
+        let mut ret = Self {
+            map,
+            comparison_cache: HashMap::new(),
+            axes: HashMap::new(),
+            dual_axes: HashMap::new(),
+            passthrough,
+        };
+        ret.recompute_cache();
+        ret
+    }
+
+    // Keys for the input. A completed sequence wins over chords, and among chords only the ones
+    // requiring the most held modifiers are used. So with Tab and Shift+Tab bound, Shift+Tab only
+    // produces the latter.
+    pub fn map_input(
+        &self,
+        context_name: &str,
+        input_value: &InputValue,
+        modifiers: Modifiers,
+        sequence_states: &mut SequenceStates,
+        now: Duration,
+    ) -> HashSet<String> {
+        if input_value.is_pressed() {
+            let completed = self.advance_sequences(context_name, input_value, modifiers, sequence_states, now);
+            if !completed.is_empty() {
+                return completed;
+            }
+        }
+
+        self.get_mapped_keys(input_value, modifiers)
+    }
+
+    pub fn get_mapped_keys(&self, input_value: &InputValue, modifiers: Modifiers) -> HashSet<String> {
+        let mut by_modifiers: HashMap<Modifiers, HashSet<&String>> = HashMap::new();
+        for lookup_key in input_value.lookup_keys() {
+            for (required, keys) in self.comparison_cache.get(&lookup_key).into_iter().flatten() {
+                by_modifiers.entry(*required).or_default().extend(keys.iter());
+            }
+        }
+
+        let matching = by_modifiers
+            .iter()
+            .filter(|(required, _)| modifiers.contains(required))
+            .collect::<Vec<_>>();
+
+        let Some(most_specific) = matching.iter().map(|(required, _)| required.count()).max() else {
+            return HashSet::new();
+        };
+
+        matching
+            .into_iter()
+            .filter(|(required, _)| required.count() == most_specific)
+            .flat_map(|(_, keys)| keys.iter().map(|key| (*key).clone()))
+            .collect()
+    }
+
+    pub fn get_bindings(&self, key: &str) -> Option<&InputBindings> {
+        self.map.get(key)
+    }
+
+    pub fn iter_bindings(&self) -> impl Iterator<Item = (&String, &InputBindings)> {
+        self.map.iter()
+    }
+
+    // Keys other than key that are bound to exactly this chord.
+    pub fn find_conflicts(&self, key: &str, chord: &InputChord) -> HashSet<String> {
+        self.map
+            .iter()
+            .filter(|(other_key, bindings)| other_key.as_str() != key && bindings.contains_chord(chord))
+            .map(|(other_key, _)| other_key.clone())
+            .collect()
+    }
+
+    // Adds the chord to key unless another key already uses it.
+    pub fn bind(&mut self, key: impl Into<String>, chord: InputChord) -> Result<(), BindingConflict> {
+        let key = key.into();
+        let conflicts = self.find_conflicts(&key, &chord);
+        if !conflicts.is_empty() {
+            return Err(BindingConflict { chord, keys: conflicts });
+        }
+
+        self.map.entry(key).or_default().insert_chord(chord);
+        self.recompute_cache();
+        Ok(())
+    }
+
+    // Adds the chord to key and removes it from every other key. Returns the keys it was taken from.
+    pub fn bind_replacing(&mut self, key: impl Into<String>, chord: InputChord) -> HashSet<String> {
+        let key = key.into();
+        let conflicts = self.find_conflicts(&key, &chord);
+        for conflict in conflicts.iter() {
+            if let Some(bindings) = self.map.get_mut(conflict) {
+                bindings.remove_chord(&chord);
+            }
+        }
+
+        self.map.entry(key).or_default().insert_chord(chord);
+        self.recompute_cache();
+        conflicts
+    }
+
+    pub fn unbind(&mut self, key: &str, chord: &InputChord) -> bool {
+        let removed = self.map.get_mut(key).is_some_and(|bindings| bindings.remove_chord(chord));
+        if removed {
+            self.recompute_cache();
+        }
+        removed
+    }
+
+    // Replaces every binding of key.
+    pub fn set_bindings(&mut self, key: impl Into<String>, bindings: InputBindings) {
+        self.map.insert(key.into(), bindings);
+        self.recompute_cache();
+    }
+
+    pub fn clear_bindings(&mut self, key: &str) {
+        if let Some(bindings) = self.map.get_mut(key) {
+            *bindings = InputBindings::default();
+            self.recompute_cache();
+        }
+    }
+
+    pub fn remove_bindings(&mut self, key: &str) -> Option<InputBindings> {
+        let removed = self.map.remove(key);
+        self.recompute_cache();
+        removed
+    }
+
+    pub fn get_axis(&self, key: &str) -> Option<&AxisBinding> {
+        self.axes.get(key)
+    }
+
+    pub fn iter_axes(&self) -> impl Iterator<Item = (&String, &AxisBinding)> {
+        self.axes.iter()
+    }
+
+    pub fn set_axis(&mut self, key: impl Into<String>, binding: AxisBinding) {
+        self.axes.insert(key.into(), binding);
+    }
+
+    pub fn remove_axis(&mut self, key: &str) -> Option<AxisBinding> {
+        self.axes.remove(key)
+    }
+
+    pub fn get_dual_axis(&self, key: &str) -> Option<&DualAxisBinding> {
+        self.dual_axes.get(key)
+    }
+
+    pub fn iter_dual_axes(&self) -> impl Iterator<Item = (&String, &DualAxisBinding)> {
+        self.dual_axes.iter()
+    }
+
+    pub fn set_dual_axis(&mut self, key: impl Into<String>, binding: DualAxisBinding) {
+        self.dual_axes.insert(key.into(), binding);
+    }
+
+    pub fn remove_dual_axis(&mut self, key: &str) -> Option<DualAxisBinding> {
+        self.dual_axes.remove(key)
+    }
+
+    fn advance_sequences(
+        &self,
+        context_name: &str,
+        input_value: &InputValue,
+        modifiers: Modifiers,
+        sequence_states: &mut SequenceStates,
+        now: Duration,
+    ) -> HashSet<String> {
+        let mut completed = HashSet::new();
+        // Releases and bare modifiers neither advance nor break a sequence, so a Ctrl+K step survives Ctrl going down.
+        if !input_value.is_pressed() || input_value.is_modifier() {
+            return completed;
         }
+
+        for (key, bindings) in self.map.iter() {
+            for sequence in bindings.sequences.iter() {
+                if sequence.steps.is_empty() {
+                    continue;
+                }
+
+                let progress = sequence_states
+                    .progress
+                    .entry((context_name.to_string(), key.clone(), sequence.clone()))
+                    .or_default();
+
+                if progress.next_step > 0 && now.saturating_sub(progress.last_step_time) > sequence.timeout {
+                    progress.next_step = 0;
+                }
+
+                if sequence.steps[progress.next_step].matches(input_value, modifiers) {
+                    progress.next_step += 1;
+                    progress.last_step_time = now;
+                } else if sequence.steps[0].matches(input_value, modifiers) {
+                    progress.next_step = 1;
+                    progress.last_step_time = now;
+                } else {
+                    progress.next_step = 0;
+                }
+
+                if progress.next_step == sequence.steps.len() {
+                    progress.next_step = 0;
+                    completed.insert(key.clone());
+                }
+            }
+        }
+        completed
     }
+
+    fn add_input_cache(&mut self, key: String, modifiers: Modifiers, input_value: InputValue) {
+        self.comparison_cache
+            .entry(input_value.binding_key())
+            .or_default()
+            .entry(modifiers)
+            .or_default()
+            .insert(key);
+    }
+
     fn add_input_value(&mut self, key: String, input_value: InputValue) {
-        if let Some(input_context) = self.map.get_mut(&key) {
-            if !input_context.values.contains(&input_value) {
-                input_context.values.insert(input_value.clone());
-                self.add_input_cache(key, input_value);
+        if let Some(bindings) = self.map.get_mut(&key) {
+            if !bindings.values.contains(&input_value) {
+                bindings.values.insert(input_value.clone());
+                self.add_input_cache(key, Modifiers::NONE, input_value);
             }
         } else {
             let mut hash_set = HashSet::new();
             hash_set.insert(input_value.clone());
             self.map.insert(
                 key.clone(),
-                InputContext {
+                InputBindings {
                 values: hash_set,
+                ..default()
             });
 
-            self.add_input_cache(key, input_value);
+            self.add_input_cache(key, Modifiers::NONE, input_value);
         }
     }
 
     fn recompute_cache(&mut self) {
         self.comparison_cache.clear();
-        for (key, input_context) in self.map.iter() {
-            for input_value in input_context.values.iter() {
-                if let Some(hash_set) = self.comparison_cache.get_mut(input_value) {
-                    hash_set.insert(key.clone());
-                } else {
-                    self.comparison_cache.insert(input_value.clone(), hashset!{key.clone()});
-                }
+        for (key, bindings) in self.map.iter() {
+            let chords = bindings.values
+                .iter()
+                .map(|input_value| (Modifiers::NONE, input_value))
+                .chain(bindings.chords.iter().map(|chord| (chord.modifiers, &chord.value)));
+
+            for (modifiers, input_value) in chords {
+                self.comparison_cache
+                    .entry(input_value.binding_key())
+                    .or_default()
+                    .entry(modifiers)
+                    .or_default()
+                    .insert(key.clone());
             }
         }
     }
 }
 
+impl InputMaps {
+
+    // Keys for the input from the active contexts, top to bottom. The first context that maps the input
+    // wins. A context without passthrough hides the contexts below it even if it maps nothing.
+    pub fn map_input(
+        &self,
+        input_value: &InputValue,
+        modifiers: Modifiers,
+        sequence_states: &mut SequenceStates,
+        now: Duration,
+    ) -> HashSet<String> {
+        for (context_name, context) in self.iter_layers() {
+            let keys = context.map_input(&context_name, input_value, modifiers, sequence_states, now);
+            if !keys.is_empty() || !context.passthrough {
+                return keys;
+            }
+        }
+
+        HashSet::new()
+    }
+
+    // Axes of the active contexts, resolved like map_input: the topmost context defining an axis wins
+    // and a context without passthrough hides everything below it.
+    pub fn active_axes(&self) -> HashMap<&String, &AxisBinding> {
+        let mut axes = HashMap::new();
+        for context in self.iter_active_contexts() {
+            for (key, binding) in context.axes.iter() {
+                axes.entry(key).or_insert(binding);
+            }
+        }
+        axes
+    }
+
+    pub fn active_dual_axes(&self) -> HashMap<&String, &DualAxisBinding> {
+        let mut dual_axes = HashMap::new();
+        for context in self.iter_active_contexts() {
+            for (key, binding) in context.dual_axes.iter() {
+                dual_axes.entry(key).or_insert(binding);
+            }
+        }
+        dual_axes
+    }
+
+    // Local contexts of the focused subtree, nearest first, followed by the stack from the top.
+    fn iter_layers(&self) -> impl Iterator<Item = (String, &InputContext)> {
+        let local = self.local_contexts
+            .iter()
+            .map(|(entity, context)| (format!("local:{}", entity), context));
+        let stack = self.active_contexts
+            .iter()
+            .rev()
+            .filter_map(|name| Some((name.clone(), self.contexts.get(name)?)));
+        local.chain(stack)
+    }
+
+    // Active contexts from the top down to the first one without passthrough.
+    fn iter_active_contexts(&self) -> impl Iterator<Item = &InputContext> {
+        let mut blocked = false;
+        self.iter_layers()
+            .map(|(_, context)| context)
+            .take_while(move |context| {
+                let visible = !blocked;
+                blocked = !context.passthrough;
+                visible
+            })
+    }
+
+    // Keys for the input without advancing sequences.
+    pub fn get_mapped_keys(&self, input_value: &InputValue, modifiers: Modifiers) -> HashSet<String> {
+        for (_, context) in self.iter_layers() {
+            let keys = context.get_mapped_keys(input_value, modifiers);
+            if !keys.is_empty() || !context.passthrough {
+                return keys;
+            }
+        }
+
+        HashSet::new()
+    }
+
+    // Repeat settings of the key in the topmost active context that binds it.
+    pub fn get_repeat(&self, key: &str) -> Option<KeyRepeat> {
+        self.get_active_bindings(key).and_then(|bindings| bindings.repeat)
+    }
+
+    // Bindings of the key in the topmost active context that binds it.
+    pub fn get_active_bindings(&self, key: &str) -> Option<&InputBindings> {
+        self.iter_active_contexts().find_map(|context| context.get_bindings(key))
+    }
+
+    // InputContext components of the focused entity and its ancestors, nearest first.
+    pub fn local_contexts(&self) -> impl Iterator<Item = (Entity, &InputContext)> {
+        self.local_contexts.iter().map(|(entity, context)| (*entity, context))
+    }
+
+    pub(crate) fn set_local_contexts(&mut self, local_contexts: Vec<(Entity, InputContext)>) {
+        self.local_contexts = local_contexts;
+    }
+
+    pub fn context(&self, name: &str) -> Option<&InputContext> {
+        self.contexts.get(name)
+    }
+
+    pub fn context_mut(&mut self, name: &str) -> Option<&mut InputContext> {
+        self.contexts.get_mut(name)
+    }
+
+    pub fn context_or_insert(&mut self, name: impl Into<String>) -> &mut InputContext {
+        self.contexts.entry(name.into()).or_default()
+    }
+
+    pub fn iter_contexts(&self) -> impl Iterator<Item = (&String, &InputContext)> {
+        self.contexts.iter()
+    }
+
+    pub fn add_context(&mut self, name: impl Into<String>, context: InputContext) {
+        self.contexts.insert(name.into(), context);
+    }
+
+    pub fn remove_context(&mut self, name: &str) -> Option<InputContext> {
+        self.active_contexts.retain(|active| active != name);
+        self.contexts.remove(name)
+    }
+
+    pub fn active_contexts(&self) -> &[String] {
+        &self.active_contexts
+    }
+
+    pub fn is_context_active(&self, name: &str) -> bool {
+        self.active_contexts.iter().any(|active| active == name)
+    }
+
+    // Returns false if there is no context with that name.
+    pub fn push_context(&mut self, name: impl Into<String>) -> bool {
+        let name = name.into();
+        if !self.contexts.contains_key(&name) {
+            warn!(context = ?name, "Input context not found");
+            return false;
+        }
+        debug!(context = ?name, "Input context pushed");
+        self.active_contexts.push(name);
+        true
+    }
+
+    pub fn pop_context(&mut self) -> Option<String> {
+        let popped = self.active_contexts.pop();
+        debug!(context = ?popped, "Input context popped");
+        popped
+    }
+
+    // Removes the topmost occurrence of the context from the stack, wherever it is.
+    pub fn remove_active_context(&mut self, name: &str) -> bool {
+        if let Some(index) = self.active_contexts.iter().rposition(|active| active == name) {
+            self.active_contexts.remove(index);
+            debug!(context = ?name, "Input context removed from stack");
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset_to_default(&mut self, context_name: &str, key: &str) {
+        let default_bindings = Self::default()
+            .contexts
+            .remove(context_name)
+            .and_then(|mut context| context.remove_bindings(key));
+        let context = self.context_or_insert(context_name);
+        match default_bindings {
+            Some(bindings) => context.set_bindings(key, bindings),
+            None => {
+                context.remove_bindings(key);
+            }
+        }
+    }
+
+    pub fn wheel_pixels_per_step(&self) -> f32 {
+        self.wheel_pixels_per_step
+    }
+
+    // Returns false and keeps the current value unless pixels is a positive number.
+    pub fn set_wheel_pixels_per_step(&mut self, pixels: f32) -> bool {
+        if !(pixels.is_finite() && pixels > 0.0) {
+            warn!(pixels, "Ignoring invalid wheel pixels per step");
+            return false;
+        }
+        self.wheel_pixels_per_step = pixels;
+        true
+    }
+
+    // Restores the default contexts and their bindings. The active stack is kept.
+    pub fn reset_all_to_default(&mut self) {
+        self.contexts = Self::default().contexts;
+    }
+}
+
 impl Default for InputMaps {
     fn default() -> Self {
-        let mut map: HashMap<String, InputContext> = HashMap::new();
+        let mut map: HashMap<String, InputBindings> = HashMap::new();
 
         map.insert(
             "ui_cancel".into(),
-            InputContext {
-                values: hashset!{InputValue::Keyboard(
-                    KeyboardInput {
-                        key_code: KeyCode::Escape,
-                        logical_key: Key::Escape,
-                        state: ButtonState::Pressed,
-                        repeat: false,
-                        window: Entity::PLACEHOLDER
-                    }
-                )}
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Escape, Key::Escape),
+                    InputValue::gamepad_button_pressed(GamepadButton::East),
+                },
+                ..default()
             }
         );
 
         map.insert(
             "ui_confirm".into(),
-            InputContext {
-                values: hashset!{InputValue::Keyboard(KeyboardInput {
-                    key_code: KeyCode::Enter,
-                    logical_key: Key::Enter,
-                    state: ButtonState::Pressed,
-                    repeat: false,
-                    window: Entity::PLACEHOLDER
-                })}
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Enter, Key::Enter),
+                    InputValue::gamepad_button_pressed(GamepadButton::South),
+                },
+                ..default()
             },
         );
 
         map.insert(
             "ui_focus_release".into(),
-            InputContext {
-                values: hashset!{InputValue::Keyboard(
-                    KeyboardInput {
-                        key_code: KeyCode::Escape,
-                        logical_key: Key::Escape,
-                        state: ButtonState::Pressed,
-                        repeat: false,
-                        window: Entity::PLACEHOLDER
-                    }
-                )}
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Escape, Key::Escape),
+                    InputValue::gamepad_button_pressed(GamepadButton::East),
+                    InputValue::mouse_button_pressed(MouseButton::Back),
+                    InputValue::Gesture(TouchGesture::Swipe(SwipeDirection::Right)),
+                },
+                ..default()
             }
         );
 
-        let mut ret = Self {
-            map,
-            comparison_cache: HashMap::new(),
-        };
+        map.insert(
+            "ui_up".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::ArrowUp, Key::ArrowUp),
+                    InputValue::gamepad_button_pressed(GamepadButton::DPadUp),
+                    InputValue::gamepad_axis_pressed(GamepadAxis::LeftStickY, AxisDirection::Positive),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
 
-        ret.recompute_cache();
+        map.insert(
+            "ui_down".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::ArrowDown, Key::ArrowDown),
+                    InputValue::gamepad_button_pressed(GamepadButton::DPadDown),
+                    InputValue::gamepad_axis_pressed(GamepadAxis::LeftStickY, AxisDirection::Negative),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
+
+        map.insert(
+            "ui_left".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::ArrowLeft, Key::ArrowLeft),
+                    InputValue::gamepad_button_pressed(GamepadButton::DPadLeft),
+                    InputValue::gamepad_axis_pressed(GamepadAxis::LeftStickX, AxisDirection::Negative),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
+
+        map.insert(
+            "ui_right".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::ArrowRight, Key::ArrowRight),
+                    InputValue::gamepad_button_pressed(GamepadButton::DPadRight),
+                    InputValue::gamepad_axis_pressed(GamepadAxis::LeftStickX, AxisDirection::Positive),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
+
+        map.insert(
+            "ui_focus_next".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Tab, Key::Tab),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
+
+        map.insert(
+            "ui_focus_previous".into(),
+            InputBindings {
+                chords: hashset!{
+                    InputChord::new(Modifiers::SHIFT, InputValue::key_pressed(KeyCode::Tab, Key::Tab)),
+                },
+                repeat: Some(KeyRepeat::NAVIGATION),
+                ..default()
+            }
+        );
+
+        let mut text_entry_map: HashMap<String, InputBindings> = HashMap::new();
+        for key in ["ui_confirm", "ui_focus_next", "ui_focus_previous"] {
+            text_entry_map.insert(key.into(), map[key].clone());
+        }
+
+        // Escape leaves the text field without also cancelling the page: the text input handles
+        // ui_text_release itself, the navigation back observer only knows ui_focus_release.
+        text_entry_map.insert(
+            "ui_text_release".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Escape, Key::Escape),
+                    InputValue::gamepad_button_pressed(GamepadButton::East),
+                },
+                ..default()
+            }
+        );
+
+        let mut gameplay_map: HashMap<String, InputBindings> = HashMap::new();
+
+        gameplay_map.insert(
+            "pause".into(),
+            InputBindings {
+                values: hashset!{
+                    InputValue::key_pressed(KeyCode::Escape, Key::Escape),
+                    InputValue::gamepad_button_pressed(GamepadButton::Start),
+                },
+                ..default()
+            }
+        );
 
-        return ret;
+        let mut gameplay_context = InputContext::new(gameplay_map, false);
+        gameplay_context.axes = analog::default_axes();
+        gameplay_context.dual_axes = analog::default_dual_axes();
+
+        let contexts = HashMap::from([
+            (MENU_CONTEXT.to_string(), InputContext::new(map, false)),
+            (TEXT_ENTRY_CONTEXT.to_string(), InputContext::new(text_entry_map, false)),
+            (GAMEPLAY_CONTEXT.to_string(), gameplay_context),
+        ]);
+
+        Self {
+            contexts,
+            active_contexts: vec![MENU_CONTEXT.to_string()],
+            local_contexts: Vec::new(),
+            axis_threshold: 0.5,
+            wheel_pixels_per_step: 50.0,
+            gesture_settings: GestureSettings::default(),
+        }
     }
 }
 
-#[derive(Clone, Debug, Component, Default)]
+// Characters compare case-insensitively, so Shift doesn't change which logical binding matches.
+fn normalize_logical_key(key: &Key) -> Key {
+    match key {
+        Key::Character(character) => Key::Character(character.to_lowercase().into()),
+        _ => key.clone(),
+    }
+}
+
+#[derive(Clone, Debug, Component, Default, HandledEvent)]
+#[event(traversal = Parent, auto_propagate)]
 pub struct MappedInputEvent {
     pub keys: HashSet<String>,
     pub keyboard_input: Option<KeyboardInput>,
+    pub input_value: Option<InputValue>,
+    // The gamepad entity for gamepad input.
+    pub gamepad: Option<Entity>,
+    // Modifiers held when the input happened.
+    pub modifiers: Modifiers,
+    // Set for OS key repeats and for the repeats InputMaps generates for keys with a KeyRepeat.
+    // Generated repeats carry no keyboard_input, so text entry only sees the OS ones.
+    pub repeat: bool,
     pub(crate) is_handled: bool,
 }
 
-impl Event for MappedInputEvent {
-    type Traversal = &'static Parent;
-    const AUTO_PROPAGATE: bool = true;
-}
-
-impl HandledEventExt for MappedInputEvent {
-    fn is_handled(&self) -> bool {
-        self.is_handled
-    }
-
-    fn set_handled(&mut self, handled: bool) {
-        self.is_handled = handled;
-    }
-}
-
 #[derive(Resource, Default)]
 pub struct KeyStates {
     pub states: HashMap<KeyCode, ButtonState>,
@@ -183,34 +1073,279 @@ impl KeyStates {
     pub fn get_state(&self, key_code: KeyCode) -> ButtonState {
         self.states.get(&key_code).unwrap_or(&ButtonState::Released).clone()
     }
+
+    pub fn is_pressed(&self, key_code: KeyCode) -> bool {
+        self.get_state(key_code).is_pressed()
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            control: self.is_pressed(KeyCode::ControlLeft) || self.is_pressed(KeyCode::ControlRight),
+            shift: self.is_pressed(KeyCode::ShiftLeft) || self.is_pressed(KeyCode::ShiftRight),
+            alt: self.is_pressed(KeyCode::AltLeft) || self.is_pressed(KeyCode::AltRight),
+            super_key: self.is_pressed(KeyCode::SuperLeft) || self.is_pressed(KeyCode::SuperRight),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SequenceProgress {
+    pub next_step: usize,
+    pub last_step_time: Duration,
+}
+
+// Progress of every bound InputSequence, keyed by context name and the key it is bound to.
+#[derive(Resource, Default)]
+pub struct SequenceStates {
+    pub progress: HashMap<(String, String, InputSequence), SequenceProgress>,
+}
+
+// Direction each gamepad axis is currently pushed in, according to InputMaps::axis_threshold.
+#[derive(Resource, Default)]
+pub struct AxisStates {
+    pub states: HashMap<(Entity, GamepadAxis), AxisDirection>,
+}
+
+impl AxisStates {
+    // Returns the previous direction if it changed.
+    pub fn set_direction(&mut self, gamepad: Entity, axis: GamepadAxis, direction: Option<AxisDirection>) -> Option<Option<AxisDirection>> {
+        let prev = match direction {
+            Some(direction) => self.states.insert((gamepad, axis), direction),
+            None => self.states.remove(&(gamepad, axis)),
+        };
+        return if prev != direction {
+            Some(prev)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_direction(&self, gamepad: Entity, axis: GamepadAxis) -> Option<AxisDirection> {
+        self.states.get(&(gamepad, axis)).copied()
+    }
 }
 
 pub fn managed_keyboard_input_system(
     mut keyboard_input_events: EventReader<KeyboardInput>,
     mut key_states: ResMut<KeyStates>,
+    mut sequence_states: ResMut<SequenceStates>,
     mut mapped_event_writer: EventWriter<MappedInputEvent>,
+    mut keyboard_layout: ResMut<KeyboardLayout>,
+    time: Res<Time>,
     input_maps: Res<InputMaps>) {
 
     for event in keyboard_input_events.read() {
         key_states.set_state(event.key_code, event.state);
+        // Only touch the layout on change, UI listing bindings reacts to it changing.
+        if event.state.is_pressed()
+            && key_states.modifiers() == Modifiers::NONE
+            && keyboard_layout.logical_key(event.key_code) != Some(&event.logical_key) {
+            keyboard_layout.learn(event.key_code, event.logical_key.clone());
+        }
         // This isn't safe. Key logging might have sensitive data.
         // debug!("Key {:?} changed state from {:?} to {:?}", event.key_code, new_state, event.state);
         let mut event = event.clone();
         event.window = Entity::PLACEHOLDER;
-        let mapped_input = if let Some(input_context) = input_maps.comparison_cache.get(&InputValue::Keyboard(event.clone())) {
-            MappedInputEvent {
-                keys: input_context.clone(),
-                keyboard_input: Some(event),
-                ..default()
-            }
-
-        } else {
-            MappedInputEvent {
-                keyboard_input: Some(event),
-                ..default()
-            }
+        let input_value = InputValue::Keyboard(event.clone());
+        let mapped_input = MappedInputEvent {
+            keys: input_maps.map_input(&input_value, key_states.modifiers(), &mut sequence_states, time.elapsed()),
+            modifiers: key_states.modifiers(),
+            repeat: event.repeat,
+            keyboard_input: Some(event),
+            input_value: Some(input_value),
+            ..default()
         };
 
         mapped_event_writer.send(mapped_input);
     }
-}
\ No newline at end of file
+}
+
+// Maps input through the active contexts with the modifiers held now and sends the MappedInputEvent.
+#[derive(SystemParam)]
+pub struct MappedInputSender<'w> {
+    input_maps: Res<'w, InputMaps>,
+    key_states: Res<'w, KeyStates>,
+    sequence_states: ResMut<'w, SequenceStates>,
+    time: Res<'w, Time>,
+    mapped_event_writer: EventWriter<'w, MappedInputEvent>,
+}
+
+impl MappedInputSender<'_> {
+    pub fn input_maps(&self) -> &InputMaps {
+        &self.input_maps
+    }
+
+    pub fn send(&mut self, input_value: InputValue, gamepad: Option<Entity>) {
+        let modifiers = self.key_states.modifiers();
+        self.mapped_event_writer.send(MappedInputEvent {
+            keys: self.input_maps.map_input(&input_value, modifiers, &mut self.sequence_states, self.time.elapsed()),
+            input_value: Some(input_value),
+            gamepad,
+            modifiers,
+            ..default()
+        });
+    }
+}
+
+pub fn managed_gamepad_input_system(
+    mut button_events: EventReader<GamepadButtonStateChangedEvent>,
+    mut axis_events: EventReader<GamepadAxisChangedEvent>,
+    mut axis_states: ResMut<AxisStates>,
+    mut sender: MappedInputSender) {
+
+    for event in button_events.read() {
+        let input_value = InputValue::GamepadButton {
+            button: event.button,
+            state: event.state,
+        };
+        sender.send(input_value, Some(event.entity));
+    }
+
+    for event in axis_events.read() {
+        let direction = AxisDirection::from_value(event.value, sender.input_maps().axis_threshold);
+        let Some(prev_direction) = axis_states.set_direction(event.entity, event.axis, direction) else {
+            continue;
+        };
+
+        // Leaving a direction releases it before the opposite one gets pressed.
+        let changes = [
+            prev_direction.map(|direction| (direction, ButtonState::Released)),
+            direction.map(|direction| (direction, ButtonState::Pressed)),
+        ];
+
+        for (direction, state) in changes.into_iter().flatten() {
+            let input_value = InputValue::GamepadAxis {
+                axis: event.axis,
+                direction,
+                state,
+            };
+            sender.send(input_value, Some(event.entity));
+        }
+    }
+}
+
+// A single wheel event never turns into more steps than this, however far it scrolled.
+const MAX_WHEEL_STEPS_PER_EVENT: f32 = 10.0;
+
+pub fn managed_mouse_input_system(
+    mut button_events: EventReader<MouseButtonInput>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut wheel_remainder: Local<Vec2>,
+    mut sender: MappedInputSender) {
+
+    for event in button_events.read() {
+        let input_value = InputValue::MouseButton {
+            button: event.button,
+            state: event.state,
+        };
+        sender.send(input_value, None);
+    }
+
+    for event in wheel_events.read() {
+        let scale = match event.unit {
+            MouseScrollUnit::Line => 1.0,
+            MouseScrollUnit::Pixel => 1.0 / sender.input_maps().wheel_pixels_per_step,
+        };
+        *wheel_remainder += Vec2::new(event.x, event.y) * scale;
+
+        if !wheel_remainder.is_finite() {
+            *wheel_remainder = Vec2::ZERO;
+            continue;
+        }
+
+        // Every whole step becomes its own event, the remainder is kept for the next scroll.
+        let whole_steps = wheel_remainder.trunc();
+        *wheel_remainder -= whole_steps;
+        let whole_steps = whole_steps.clamp(Vec2::splat(-MAX_WHEEL_STEPS_PER_EVENT), Vec2::splat(MAX_WHEEL_STEPS_PER_EVENT));
+
+        let mut steps = Vec::new();
+        let vertical = if whole_steps.y > 0.0 { WheelDirection::Up } else { WheelDirection::Down };
+        steps.extend(std::iter::repeat_n(vertical, whole_steps.y.abs() as usize));
+        let horizontal = if whole_steps.x > 0.0 { WheelDirection::Right } else { WheelDirection::Left };
+        steps.extend(std::iter::repeat_n(horizontal, whole_steps.x.abs() as usize));
+
+        for direction in steps {
+            sender.send(InputValue::MouseWheel(direction), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key_code: KeyCode, state: ButtonState) -> InputValue {
+        InputValue::Keyboard(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        })
+    }
+
+    fn press(key_code: KeyCode) -> InputValue {
+        key(key_code, ButtonState::Pressed)
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    // "top" is G then G, at most 500ms apart.
+    fn sequence_context() -> InputContext {
+        let mut context = InputContext::default();
+        context.set_bindings("top", InputBindings {
+            sequences: hashset!{
+                InputSequence {
+                    steps: vec![InputChord::new(Modifiers::NONE, press(KeyCode::KeyG)); 2],
+                    timeout: ms(500),
+                },
+            },
+            ..default()
+        });
+        context
+    }
+
+    #[test]
+    fn most_specific_chord_wins() {
+        let mut context = InputContext::default();
+        context.bind("save", InputChord::new(Modifiers::CONTROL, press(KeyCode::KeyS))).unwrap();
+        context.bind("save_as", InputChord::new(Modifiers::CONTROL.with(Modifiers::SHIFT), press(KeyCode::KeyS))).unwrap();
+
+        let s = press(KeyCode::KeyS);
+        assert_eq!(context.get_mapped_keys(&s, Modifiers::CONTROL.with(Modifiers::SHIFT)), hashset!{"save_as".to_string()});
+        assert_eq!(context.get_mapped_keys(&s, Modifiers::CONTROL), hashset!{"save".to_string()});
+        // Extra modifiers still match the less specific chord.
+        assert_eq!(context.get_mapped_keys(&s, Modifiers::CONTROL.with(Modifiers::ALT)), hashset!{"save".to_string()});
+        assert!(context.get_mapped_keys(&s, Modifiers::NONE).is_empty());
+    }
+
+    #[test]
+    fn sequence_resets_on_a_wrong_key() {
+        let context = sequence_context();
+        let mut states = SequenceStates::default();
+        let mut map = |input_value: InputValue, now: Duration| context.map_input(MENU_CONTEXT, &input_value, Modifiers::NONE, &mut states, now);
+
+        assert!(map(press(KeyCode::KeyG), ms(0)).is_empty());
+        assert!(map(press(KeyCode::KeyH), ms(100)).is_empty());
+        // The second G starts over.
+        assert!(map(press(KeyCode::KeyG), ms(200)).is_empty());
+        // Releases and bare modifiers don't break it.
+        assert!(map(key(KeyCode::KeyG, ButtonState::Released), ms(250)).is_empty());
+        assert!(map(press(KeyCode::ShiftLeft), ms(260)).is_empty());
+        assert_eq!(map(press(KeyCode::KeyG), ms(300)), hashset!{"top".to_string()});
+    }
+
+    #[test]
+    fn sequence_times_out() {
+        let context = sequence_context();
+        let mut states = SequenceStates::default();
+        let mut map = |input_value: InputValue, now: Duration| context.map_input(MENU_CONTEXT, &input_value, Modifiers::NONE, &mut states, now);
+
+        assert!(map(press(KeyCode::KeyG), ms(0)).is_empty());
+        assert!(map(press(KeyCode::KeyG), ms(501)).is_empty());
+        // The late G was the first step of a new try.
+        assert_eq!(map(press(KeyCode::KeyG), ms(1001)), hashset!{"top".to_string()});
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 pub mod focus;
 pub mod input_map;
+pub mod recording;
+pub mod prompts;
 
 pub struct InputPlugin;
 
@@ -8,5 +10,7 @@ impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(input_map::InputMapPlugin);
         app.add_plugins(focus::UiFocusPlugin);
+        app.add_plugins(recording::InputRecordingPlugin);
+        app.add_plugins(prompts::InputPromptsPlugin);
     }
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use crate::ui::input::input_map::{InputChord, InputMaps, InputValue, MappedInputEvent};
+use crate::ui::input::input_map::key_names::KeyboardLayout;
+
+pub struct InputPromptsPlugin;
+
+impl Plugin for InputPromptsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveInputDevice>();
+        app.init_resource::<InputGlyphs>();
+        app.add_systems(PostUpdate, active_input_device_system);
+    }
+}
+
+#[derive(Hash, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+    Touch,
+}
+
+impl InputDevice {
+    pub fn of(input_value: &InputValue) -> Self {
+        match input_value {
+            InputValue::Keyboard(_)
+            | InputValue::LogicalKey { .. }
+            | InputValue::MouseButton { .. }
+            | InputValue::MouseWheel(_) => InputDevice::KeyboardMouse,
+            InputValue::GamepadButton { .. } | InputValue::GamepadAxis { .. } => InputDevice::Gamepad,
+            InputValue::Gesture(_) => InputDevice::Touch,
+        }
+    }
+}
+
+// The device the player pressed something on last. Prompts show the bindings of this device.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActiveInputDevice(pub InputDevice);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputGlyph {
+    Text(String),
+    // Text is used where the icon can't be shown, e.g. inside a Text.
+    Icon { image: Handle<Image>, text: String },
+}
+
+impl InputGlyph {
+    pub fn text(&self) -> &str {
+        match self {
+            InputGlyph::Text(text) => text,
+            InputGlyph::Icon { text, .. } => text,
+        }
+    }
+}
+
+// Glyphs replacing the display names of bound values. Looked up by InputValue::binding_key.
+#[derive(Resource, Clone, Debug)]
+pub struct InputGlyphs {
+    glyphs: HashMap<InputValue, InputGlyph>,
+}
+
+impl InputGlyphs {
+    pub fn get(&self, input_value: &InputValue) -> Option<&InputGlyph> {
+        self.glyphs.get(&input_value.binding_key())
+    }
+
+    pub fn insert(&mut self, input_value: InputValue, glyph: InputGlyph) {
+        self.glyphs.insert(input_value.binding_key(), glyph);
+    }
+
+    pub fn remove(&mut self, input_value: &InputValue) -> Option<InputGlyph> {
+        self.glyphs.remove(&input_value.binding_key())
+    }
+}
+
+impl Default for InputGlyphs {
+    fn default() -> Self {
+        let glyphs = [
+            (GamepadButton::South, "Ⓐ"),
+            (GamepadButton::East, "Ⓑ"),
+            (GamepadButton::West, "Ⓧ"),
+            (GamepadButton::North, "Ⓨ"),
+            (GamepadButton::DPadUp, "⮝"),
+            (GamepadButton::DPadDown, "⮟"),
+            (GamepadButton::DPadLeft, "⮜"),
+            (GamepadButton::DPadRight, "⮞"),
+            (GamepadButton::Start, "☰"),
+            (GamepadButton::Select, "⧉"),
+            (GamepadButton::LeftTrigger, "LB"),
+            (GamepadButton::RightTrigger, "RB"),
+            (GamepadButton::LeftTrigger2, "LT"),
+            (GamepadButton::RightTrigger2, "RT"),
+        ];
+
+        Self {
+            glyphs: glyphs
+                .into_iter()
+                .map(|(button, glyph)| (InputValue::gamepad_button_pressed(button), InputGlyph::Text(glyph.to_string())))
+                .collect(),
+        }
+    }
+}
+
+// Looks up how to show the binding of an action, e.g. "Press Ⓑ to go back".
+#[derive(SystemParam)]
+pub struct InputPrompts<'w> {
+    pub input_maps: Res<'w, InputMaps>,
+    pub keyboard_layout: Res<'w, KeyboardLayout>,
+    pub active_device: Res<'w, ActiveInputDevice>,
+    pub glyphs: Res<'w, InputGlyphs>,
+}
+
+impl InputPrompts<'_> {
+    // Whether anything prompts depend on changed since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.input_maps.is_changed()
+            || self.keyboard_layout.is_changed()
+            || self.active_device.is_changed()
+            || self.glyphs.is_changed()
+    }
+
+    // The binding shown for the action: one for the active device if it has one, otherwise any.
+    // Chords with fewer modifiers are preferred.
+    pub fn action_chord(&self, action: &str) -> Option<InputChord> {
+        let bindings = self.input_maps.get_active_bindings(action)?;
+        let mut chords = bindings.iter_chords().collect::<Vec<_>>();
+        // Hash set order is random, keep the prompt stable.
+        chords.sort_by_cached_key(|chord| (
+            InputDevice::of(&chord.value) != self.active_device.0,
+            chord.modifiers.count(),
+            format!("{:?}", chord),
+        ));
+        chords.into_iter().next()
+    }
+
+    pub fn chord_glyph(&self, chord: &InputChord) -> InputGlyph {
+        let prefix = chord.modifiers.display_prefix();
+        match self.glyphs.get(&chord.value) {
+            Some(InputGlyph::Text(text)) => InputGlyph::Text(format!("{}{}", prefix, text)),
+            Some(InputGlyph::Icon { image, text }) => InputGlyph::Icon {
+                image: image.clone(),
+                text: format!("{}{}", prefix, text),
+            },
+            None => InputGlyph::Text(chord.display_name(&self.keyboard_layout)),
+        }
+    }
+
+    pub fn action_glyph(&self, action: &str) -> Option<InputGlyph> {
+        self.action_chord(action).map(|chord| self.chord_glyph(&chord))
+    }
+
+    // Replaces every [action] in the template with the text of its glyph. Unbound actions are left as is.
+    pub fn format(&self, template: &str) -> String {
+        let mut formatted = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('[') {
+            let Some(length) = rest[start..].find(']') else {
+                break;
+            };
+            let action = &rest[start + 1..start + length];
+            formatted.push_str(&rest[..start]);
+            match self.action_glyph(action) {
+                Some(glyph) => formatted.push_str(glyph.text()),
+                None => formatted.push_str(&rest[start..=start + length]),
+            }
+            rest = &rest[start + length + 1..];
+        }
+        formatted.push_str(rest);
+        formatted
+    }
+}
+
+fn active_input_device_system(mut mapped_events: EventReader<MappedInputEvent>, mut active_device: ResMut<ActiveInputDevice>) {
+    let last_pressed = mapped_events
+        .read()
+        .filter_map(|event| event.input_value.as_ref())
+        .filter(|input_value| input_value.is_pressed())
+        .last();
+
+    if let Some(input_value) = last_pressed {
+        let device = InputDevice::of(input_value);
+        if active_device.0 != device {
+            debug!(?device, "Active input device changed");
+            active_device.0 = device;
+        }
+    }
+}
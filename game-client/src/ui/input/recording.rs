@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+use bevy::asset::ron;
+use bevy::core::FrameCount;
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
+use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonStateChangedEvent};
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{MouseButtonInput, MouseWheel};
+use bevy::input::touch::TouchInput;
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use serde::{Deserialize, Serialize};
+
+// Records the raw device events with the frame they arrived on, and plays them back so they reach
+// the managed input systems on the same relative frames. Handy for reproducing UI bugs and for
+// driving a headless app through the pages:
+//
+// app.insert_resource(InputPlayback::new(InputRecording::load(path)?));
+// while !app.world().resource::<InputPlayback>().is_finished() {
+//     app.update();
+// }
+//
+// Set GAME_RECORD_INPUT to a file path to record a session (saved on exit), and GAME_REPLAY_INPUT
+// to play one back on startup. While playing back, every frame advances the virtual Time by the
+// recording's frame duration, so key repeat, sequence timeouts, gestures and holds see the same
+// timings as when recording. Gamepads are matched to the connected ones in the order they first show
+// up in the recording.
+pub struct InputRecordingPlugin;
+
+pub const RECORD_INPUT_ENV: &str = "GAME_RECORD_INPUT";
+pub const REPLAY_INPUT_ENV: &str = "GAME_REPLAY_INPUT";
+
+impl Plugin for InputRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        // Headless apps don't have the window and gilrs plugins sending these.
+        app.add_event::<KeyboardInput>();
+        app.add_event::<GamepadButtonStateChangedEvent>();
+        app.add_event::<GamepadAxisChangedEvent>();
+        app.add_event::<MouseButtonInput>();
+        app.add_event::<MouseWheel>();
+        app.add_event::<TouchInput>();
+
+        let mut recorder = InputRecorder::default();
+        if let Ok(path) = std::env::var(RECORD_INPUT_ENV) {
+            info!(?path, "Recording input");
+            recorder.save_path = Some(PathBuf::from(path));
+            recorder.start(0);
+        }
+        app.insert_resource(recorder);
+
+        if let Ok(path) = std::env::var(REPLAY_INPUT_ENV) {
+            match InputRecording::load(Path::new(&path)) {
+                Ok(recording) => {
+                    info!(?path, events = recording.events.len(), "Replaying input");
+                    app.insert_resource(InputPlayback::new(recording));
+                }
+                Err(e) => error!("Error: loading input recording from {:?} failed: {}", path, e),
+            }
+        }
+
+        app.add_systems(
+            PreUpdate,
+            (record_input_system, playback_input_system.run_if(resource_exists::<InputPlayback>))
+                .chain()
+                .after(InputSystem)
+        );
+        app.add_systems(First, playback_time_system.before(TimeSystem).run_if(resource_exists::<InputPlayback>));
+        app.add_systems(Last, save_recording_on_exit_system);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    Keyboard(KeyboardInput),
+    GamepadButton(GamepadButtonStateChangedEvent),
+    GamepadAxis(GamepadAxisChangedEvent),
+    MouseButton(MouseButtonInput),
+    MouseWheel(MouseWheel),
+    Touch(TouchInput),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    // Frames since the recording started.
+    pub frame: u32,
+    pub event: RecordedEvent,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub events: Vec<RecordedInput>,
+    // Average virtual time between the recorded frames, each played back frame takes exactly this long.
+    #[serde(default = "default_frame_duration")]
+    pub frame_duration: Duration,
+}
+
+fn default_frame_duration() -> Duration {
+    Duration::from_secs(1) / 60
+}
+
+impl Default for InputRecording {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            frame_duration: default_frame_duration(),
+        }
+    }
+}
+
+impl InputRecording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.events.last().map_or(0, |recorded| recorded.frame + 1)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InputRecorder {
+    // Frame the current recording started on, None when not recording.
+    start_frame: Option<u32>,
+    // Elapsed virtual time when the recording started.
+    start_elapsed: Duration,
+    recording: InputRecording,
+    // Where the recording is written when the app exits.
+    pub save_path: Option<PathBuf>,
+}
+
+impl InputRecorder {
+    // Drops whatever was recorded before.
+    pub fn start(&mut self, frame: u32) {
+        self.start_frame = Some(frame);
+        self.start_elapsed = Duration::ZERO;
+        self.recording = InputRecording::default();
+    }
+
+    pub fn stop(&mut self) -> Option<InputRecording> {
+        self.start_frame.take()?;
+        Some(std::mem::take(&mut self.recording))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.start_frame.is_some()
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    fn update_frame_duration(&mut self, frame: u32, elapsed: Duration) {
+        let Some(start_frame) = self.start_frame else {
+            return;
+        };
+        let frames = frame.wrapping_sub(start_frame);
+        if frames == 0 {
+            self.start_elapsed = elapsed;
+        } else if elapsed > self.start_elapsed {
+            self.recording.frame_duration = (elapsed - self.start_elapsed) / frames;
+        }
+    }
+
+    fn record(&mut self, frame: u32, event: RecordedEvent) {
+        if let Some(start_frame) = self.start_frame {
+            self.recording.events.push(RecordedInput {
+                frame: frame.wrapping_sub(start_frame),
+                event,
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct InputPlayback {
+    recording: InputRecording,
+    // Set on the first frame of playback.
+    start_frame: Option<u32>,
+    next_event: usize,
+    // Recorded gamepad entity to the one its events are sent for now.
+    gamepads: HashMap<Entity, Entity>,
+    // The strategy to go back to once playback is finished, Some while playback drives the time.
+    previous_time_strategy: Option<TimeUpdateStrategy>,
+}
+
+impl InputPlayback {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            start_frame: None,
+            next_event: 0,
+            gamepads: HashMap::new(),
+            previous_time_strategy: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_event >= self.recording.events.len()
+    }
+}
+
+// The device events a recording is made of.
+#[derive(SystemParam)]
+pub struct RecordedEventReaders<'w, 's> {
+    keyboard: EventReader<'w, 's, KeyboardInput>,
+    gamepad_button: EventReader<'w, 's, GamepadButtonStateChangedEvent>,
+    gamepad_axis: EventReader<'w, 's, GamepadAxisChangedEvent>,
+    mouse_button: EventReader<'w, 's, MouseButtonInput>,
+    mouse_wheel: EventReader<'w, 's, MouseWheel>,
+    touch: EventReader<'w, 's, TouchInput>,
+}
+
+impl RecordedEventReaders<'_, '_> {
+    fn clear(&mut self) {
+        self.keyboard.clear();
+        self.gamepad_button.clear();
+        self.gamepad_axis.clear();
+        self.mouse_button.clear();
+        self.mouse_wheel.clear();
+        self.touch.clear();
+    }
+}
+
+#[derive(SystemParam)]
+pub struct RecordedEventWriters<'w> {
+    keyboard: EventWriter<'w, KeyboardInput>,
+    gamepad_button: EventWriter<'w, GamepadButtonStateChangedEvent>,
+    gamepad_axis: EventWriter<'w, GamepadAxisChangedEvent>,
+    mouse_button: EventWriter<'w, MouseButtonInput>,
+    mouse_wheel: EventWriter<'w, MouseWheel>,
+    touch: EventWriter<'w, TouchInput>,
+}
+
+pub fn record_input_system(
+    mut events: RecordedEventReaders,
+    mut recorder: ResMut<InputRecorder>,
+    frame_count: Res<FrameCount>,
+    time: Res<Time>) {
+
+    if !recorder.is_recording() {
+        events.clear();
+        return;
+    }
+
+    // Window entities differ between runs, the managed systems don't use them.
+    let frame = frame_count.0;
+    recorder.update_frame_duration(frame, time.elapsed());
+    for event in events.keyboard.read() {
+        recorder.record(frame, RecordedEvent::Keyboard(KeyboardInput { window: Entity::PLACEHOLDER, ..event.clone() }));
+    }
+    for event in events.gamepad_button.read() {
+        recorder.record(frame, RecordedEvent::GamepadButton(*event));
+    }
+    for event in events.gamepad_axis.read() {
+        recorder.record(frame, RecordedEvent::GamepadAxis(*event));
+    }
+    for event in events.mouse_button.read() {
+        recorder.record(frame, RecordedEvent::MouseButton(MouseButtonInput { window: Entity::PLACEHOLDER, ..*event }));
+    }
+    for event in events.mouse_wheel.read() {
+        recorder.record(frame, RecordedEvent::MouseWheel(MouseWheel { window: Entity::PLACEHOLDER, ..*event }));
+    }
+    for event in events.touch.read() {
+        recorder.record(frame, RecordedEvent::Touch(TouchInput { window: Entity::PLACEHOLDER, ..*event }));
+    }
+}
+
+// Steps the virtual time by the recording's frame duration until playback is finished, then restores
+// the previous update strategy.
+pub fn playback_time_system(mut playback: ResMut<InputPlayback>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    if playback.is_finished() {
+        if let Some(previous) = playback.previous_time_strategy.take() {
+            *strategy = previous;
+        }
+        return;
+    }
+
+    if playback.previous_time_strategy.is_none() {
+        let frame_duration = playback.recording.frame_duration;
+        playback.previous_time_strategy = Some(std::mem::replace(&mut *strategy, TimeUpdateStrategy::ManualDuration(frame_duration)));
+    }
+}
+
+// Runs after recording so replayed events don't end up in a recording made at the same time.
+pub fn playback_input_system(
+    mut commands: Commands,
+    mut playback: ResMut<InputPlayback>,
+    connected_gamepads: Query<Entity, With<Gamepad>>,
+    mut events: RecordedEventWriters,
+    frame_count: Res<FrameCount>) {
+
+    let start_frame = *playback.start_frame.get_or_insert(frame_count.0);
+    let frame = frame_count.0.wrapping_sub(start_frame);
+    let playback = &mut *playback;
+
+    // The n-th gamepad of the recording plays on the n-th connected one, or on a stand-in when there
+    // aren't enough. The managed systems only use the entity to tell gamepads apart.
+    let mut connected_gamepads = connected_gamepads.iter().collect::<Vec<_>>();
+    connected_gamepads.sort();
+    let mut remap_gamepad = |gamepads: &mut HashMap<Entity, Entity>, recorded: Entity| -> Entity {
+        let index = gamepads.len();
+        *gamepads.entry(recorded).or_insert_with(|| {
+            connected_gamepads
+                .get(index)
+                .copied()
+                .unwrap_or_else(|| commands.spawn(Name::new("Replayed gamepad")).id())
+        })
+    };
+
+    while let Some(recorded) = playback.recording.events.get(playback.next_event) {
+        if recorded.frame > frame {
+            break;
+        }
+
+        match recorded.event.clone() {
+            RecordedEvent::Keyboard(event) => { events.keyboard.send(event); }
+            RecordedEvent::GamepadButton(event) => {
+                let entity = remap_gamepad(&mut playback.gamepads, event.entity);
+                events.gamepad_button.send(GamepadButtonStateChangedEvent { entity, ..event });
+            }
+            RecordedEvent::GamepadAxis(event) => {
+                let entity = remap_gamepad(&mut playback.gamepads, event.entity);
+                events.gamepad_axis.send(GamepadAxisChangedEvent { entity, ..event });
+            }
+            RecordedEvent::MouseButton(event) => { events.mouse_button.send(event); }
+            RecordedEvent::MouseWheel(event) => { events.mouse_wheel.send(event); }
+            RecordedEvent::Touch(event) => { events.touch.send(event); }
+        }
+        playback.next_event += 1;
+    }
+}
+
+pub fn save_recording_on_exit_system(mut exit_events: EventReader<AppExit>, mut recorder: ResMut<InputRecorder>) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+
+    let Some(path) = recorder.save_path.clone() else {
+        return;
+    };
+    let Some(recording) = recorder.stop() else {
+        return;
+    };
+
+    match recording.save(&path) {
+        Ok(()) => info!(?path, events = recording.events.len(), "Input recording saved"),
+        Err(e) => error!("Error: saving input recording to {:?} failed: {}", path, e),
+    }
+}
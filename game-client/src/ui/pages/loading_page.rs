@@ -1,6 +1,7 @@
 use std::time::Duration;
 use bevy::ecs::system::RunSystemOnce;
 use bevy::prelude::*;
+use crate::event_system::scheduler::EventScheduler;
 use crate::scene_system::{GenericUiSceneCreator, InstantSpawnState, SpawnState, UiSceneCreatorFn};
 use crate::ui::ui_navigation::{UiNavigationEvent};
 
@@ -9,28 +10,21 @@ pub struct LoadingPagePlugin;
 impl Plugin for LoadingPagePlugin {
     fn build(&self, app: &mut App) {
         _ = app.world_mut().run_system_once(loading_page.get_system());
-        app.register_type::<LoadingPageTimer>();
-        app.add_systems(Update, loading_page_timer_system);
+        app.register_type::<LoadingPage>();
+        app.add_observer(loading_page_added_observer);
     }
 }
 
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Default)]
 #[reflect(Component)]
-struct LoadingPageTimer {
-    timer: Timer,
-}
-
-fn loading_page_timer_system(mut commands: Commands,time: Res<Time>, mut query: Query<(Entity, &mut LoadingPageTimer)>) {
-    for (entity, mut timer) in query.iter_mut() {
-        timer.timer.tick(time.delta());
-        if timer.timer.times_finished_this_tick() > 0 {
-            // commands.modify_context(entity, |nav: &mut UiNavigation| {
-            //     println!("Loading finished");
-            //     nav.next_path = Some(Vec::new());
-            // });
-            commands.trigger_targets(UiNavigationEvent::SetPath(Vec::new()), entity);
-        }
-    }
+struct LoadingPage;
+
+fn loading_page_added_observer(trigger: Trigger<OnAdd, LoadingPage>, mut scheduler: EventScheduler) {
+    // commands.modify_context(entity, |nav: &mut UiNavigation| {
+    //     println!("Loading finished");
+    //     nav.next_path = Some(Vec::new());
+    // });
+    scheduler.trigger_after(Duration::from_secs(1), UiNavigationEvent::SetPath(Vec::new()), trigger.entity());
 }
 
 fn loading_page(_: &mut World) -> anyhow::Result<GenericUiSceneCreator> {
@@ -38,9 +32,7 @@ fn loading_page(_: &mut World) -> anyhow::Result<GenericUiSceneCreator> {
 
     new_world.spawn(
         (Text::new("Loading..."),
-         LoadingPageTimer {
-             timer: Timer::new(Duration::from_secs(1), TimerMode::Once)
-         }
+         LoadingPage,
         ),
     );
 
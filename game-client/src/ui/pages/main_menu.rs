@@ -4,8 +4,9 @@ use bevy::prelude::*;
 use crate::scene_system::{GenericUiSceneCreator, InstantSpawnState, SpawnState, UiSceneCreatorFn};
 use crate::ui::components::FULL_SIZE_NODE;
 use crate::ui::components::text_creator::TextCreator;
+use crate::ui::components::text_input::BasicTextInput;
 use crate::ui::interaction::interaction_style::{NodeStyle, MainStyle};
-use crate::ui::input::focus::InputFocusPolicy;
+use crate::ui::input::focus::{AutoFocus, InputFocusPolicy};
 use crate::ui::theme::ThemeElement;
 use crate::ui::ui_navigation::UiNavigationEvent;
 
@@ -78,6 +79,7 @@ fn main_menu(_: &mut World) -> anyhow::Result<GenericUiSceneCreator> {
                     main_menu_button.clone(),
                     UiNavigationEvent::AppendPath(vec!["new_game".to_string()]),
                     TextCreator::from("New Game"),
+                    AutoFocus,
                 ));
 
             parent.spawn((
@@ -96,6 +98,7 @@ fn main_menu(_: &mut World) -> anyhow::Result<GenericUiSceneCreator> {
                     TextCreator::from("H "),
                     ThemeElement::from_class("text_input"),
                     InputFocusPolicy::All,
+                    BasicTextInput::default(),
                 )
             );
         });
@@ -3,10 +3,17 @@ use bevy::log::tracing_subscriber::filter::combinator::And;
 use crate::ui::interaction::interaction_style::NodeStyle;
 use bevy::prelude::*;
 use crate::event_system::{HandledEventExt, UnhandledEventCommandsExt, UnhandledEventTriggerExt, UnhandledEventWorldExt};
+use crate::ui::theme::selector::Selector;
+use bevy::ecs::system::SystemParam;
+use crate::ui::theme::stylesheet::{theme_stylesheet_system, ThemeSource, ThemeStylesheet, ThemeStylesheetPlugin};
+
+pub mod selector;
+pub mod stylesheet;
 
 #[derive(Component, Clone, Reflect, Default, Hash)]
 #[reflect(Component)]
 pub struct ThemeElement {
+    // Whitespace separated, e.g. "button primary".
     pub class: Cow<'static, str>,
     pub id: Cow<'static, str>,
 }
@@ -18,10 +25,19 @@ impl ThemeElement {
             id: Cow::Borrowed(""),
         }
     }
+
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.class.split_whitespace()
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes().any(|other| other == class)
+    }
 }
 
 
 // Default theme will be the resource, the rest will be the component.
+// Stylesheet keys are selectors, see Selector.
 #[derive(Resource, Default, Component)]
 pub struct Theme {
     pub class_stylesheet: std::collections::HashMap<Cow<'static, str>, NodeStyle>,
@@ -33,57 +49,77 @@ impl Plugin for ThemePlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<ThemeElement>();
         app.insert_resource(Theme::default());
-        app.add_systems(PostUpdate, on_changed_theme_system);
+        app.add_plugins(ThemeStylesheetPlugin);
+        app.add_systems(PostUpdate, (theme_stylesheet_system, on_changed_theme_system).chain());
     }
 }
 
 
+// Parsed selectors per stylesheet, None for themes filled in code. A stylesheet's entries are dropped when it
+// changes or is unloaded.
+type SelectorCache = std::collections::HashMap<Option<AssetId<ThemeStylesheet>>, std::collections::HashMap<Cow<'static, str>, Option<Selector>>>;
+
+#[derive(SystemParam)]
+struct ThemeHierarchy<'w, 's> {
+    // Themes of subtrees, with the source filling them if any.
+    theme_query: Query<'w, 's, (&'static Theme, Option<&'static ThemeSource>)>,
+    element_query: Query<'w, 's, &'static ThemeElement>,
+    parent_query: Query<'w, 's, &'static Parent>,
+    children: Query<'w, 's, &'static Children>,
+    default_theme: Res<'w, Theme>,
+    default_source: Option<Res<'w, ThemeSource>>,
+}
+
 // Detects change and creates triggers that propagate either up or down to update element.
 // Events that propagate down will echo upward. They are theme updates that will require elements to reconfigure.
 fn on_changed_theme_system(
     mut commands: Commands,
-    theme_query: Query<&Theme>,
     changed_theme_query: Query<Entity, Changed<Theme>>,
-    element_query: Query<(&ThemeElement)>,
-    changed_element_query: Query<(Entity, &ThemeElement), Changed<ThemeElement>>,
-    parent_query: Query<&Parent>,
-    children: Query<&Children>,
-    default_theme: Res<Theme>) {
+    changed_element_query: Query<Entity, Changed<ThemeElement>>,
+    all_element_query: Query<(Entity, &ThemeElement)>,
+    hierarchy: ThemeHierarchy,
+    mut asset_events: EventReader<AssetEvent<ThemeStylesheet>>,
+    mut selectors: Local<SelectorCache>) {
     // Algorithm :
-    // - Collect changed theme elements into a hashmap
-    // - Create a set of entities for changed themes. Ensure subtrees do not intersect by removing repeated parents.
-    // - Iterate over the children of the collected theme entities, add theme elements to the initial elements hashmap.
+    // - Collect the roots of what needs restyling: changed theme elements (selectors of the elements below
+    //   may depend on them) and entities with a changed theme.
+    // - Drop the roots below another root, so every subtree is walked once.
+    // - Collect the theme elements of the remaining subtrees, or all of them when the default theme changed.
     // - Iterate over ancestors of each theme element, find the style for it.
     use std::collections::HashSet;
     use std::collections::HashMap;
 
-    let theme_entities: HashSet<Entity> = changed_theme_query.iter().collect();
-
-    // First lets collect the elements
-    let mut elements: HashMap<Entity, ThemeElement> = HashMap::with_capacity(changed_element_query.iter().count() + theme_entities.len() * 30);
+    let ThemeHierarchy { theme_query, element_query, parent_query, children, default_theme, default_source } = hierarchy;
 
-    for (entity, element) in changed_element_query.iter() {
-        elements.insert(entity, element.clone());
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::Modified { id } | AssetEvent::Removed { id } => {
+                selectors.remove(&Some(*id));
+            }
+            _ => {}
+        }
     }
 
-    for entity in theme_entities.iter() {
-        let mut is_unique = true;
-        for ancestor in parent_query.iter_ancestors(entity.clone()) {
-            if theme_entities.contains(&ancestor) {
-                is_unique = false;
-                break;
-            }
+    let mut elements: HashMap<Entity, ThemeElement> = HashMap::new();
+
+    if default_theme.is_changed() {
+        // The global theme changed (e.g. its stylesheet reloaded), any element may fall back to it.
+        for (entity, element) in all_element_query.iter() {
+            elements.insert(entity, element.clone());
         }
-        if is_unique {
-            // Does not have a shared parent.
-            // Add this entity and it's ThemeElement children to "elements".
-            if let Ok(self_element) = element_query.get(entity.clone()) {
-                elements.insert(entity.clone(), self_element.clone());
+    } else {
+        let roots: HashSet<Entity> = changed_element_query
+            .iter()
+            .chain(changed_theme_query.iter())
+            .collect();
+
+        for root in roots.iter() {
+            if parent_query.iter_ancestors(*root).any(|ancestor| roots.contains(&ancestor)) {
+                continue;
             }
-
-            for descendant in children.iter_descendants(entity.clone()) {
-                if let Ok(element) = element_query.get(descendant) {
-                    elements.insert(descendant.clone(), element.clone());
+            for entity in std::iter::once(*root).chain(children.iter_descendants(*root)) {
+                if let Ok(element) = element_query.get(entity) {
+                    elements.insert(entity, element.clone());
                 }
             }
         }
@@ -91,26 +127,43 @@ fn on_changed_theme_system(
 
     // Now we have a list of all elements that need updating. We will search in their ancestors to see what they have.
     for (entity, element) in elements.iter() {
-        // Search through the acnestors. if any is a Theme, try to get the style from it.
-        let mut theme_found = false;
-        for ancestor in parent_query.iter_ancestors(entity.clone()) {
-            if let Ok(theme) = theme_query.get(ancestor) {
-                if let Some(style) = theme.class_stylesheet.get(&element.class) {
-                    commands.entity(entity.clone()).insert(style.clone());
-
-                    // We managed to find the theme style for this element
-                    theme_found = true;
-                    break;
+        let ancestors = parent_query.iter_ancestors(*entity).collect::<Vec<_>>();
+        let ancestor_elements = ancestors.iter().map(|ancestor| element_query.get(*ancestor).ok()).collect::<Vec<_>>();
+
+        // Themes from the nearest ancestor up, the global default theme last.
+        let themes = ancestors
+            .iter()
+            .filter_map(|ancestor| theme_query.get(*ancestor).ok())
+            .chain(std::iter::once((&*default_theme, default_source.as_deref())));
+
+        // The most specific matching rule wins, then the nearest theme. The selector text only keeps ties stable.
+        let mut best = None;
+        for (distance, (theme, source)) in themes.enumerate() {
+            let selectors = selectors.entry(source.map(|source| source.handle.id())).or_default();
+            for (key, style) in theme.class_stylesheet.iter() {
+                let selector = selectors.entry(key.clone()).or_insert_with(|| {
+                    let selector = Selector::parse(key);
+                    if selector.is_none() {
+                        warn!(selector = ?key, "Invalid theme selector");
+                    }
+                    selector
+                });
+                let Some(selector) = selector else {
+                    continue;
+                };
+                if !selector.matches(element, &ancestor_elements) {
+                    continue;
+                }
+                let rank = (selector.specificity(), std::cmp::Reverse(distance), key.clone());
+                if best.as_ref().is_none_or(|(best_rank, _)| rank > *best_rank) {
+                    best = Some((rank, style));
                 }
             }
         }
 
-        // We didn't find a theme component for it. use the global default theme.
-        if !theme_found {
-            if let Some(style) = default_theme.class_stylesheet.get(&element.class) {
-                commands.entity(entity.clone()).insert(style.clone());
-            }
+        if let Some((_, style)) = best {
+            commands.entity(*entity).insert(style.clone());
         }
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,175 @@
+use crate::ui::theme::ThemeElement;
+
+// Keys of Theme::class_stylesheet are selectors over ThemeElements:
+//
+// "button"                - class, a leading dot is optional: ".button"
+// "#save"                 - id
+// "button.primary"        - all of the classes (and the id, if any) on the same element
+// "dialog button"         - a button anywhere below a dialog
+// "dialog > button"       - a button whose parent is a dialog
+//
+// The rule with the highest specificity wins: ids first, then classes. Equal ones go to the nearest theme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selector {
+    // The combinator joins a part to the one before it, the first part's is unused.
+    parts: Vec<(Combinator, CompoundSelector)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct CompoundSelector {
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+// (ids, classes) summed over all parts, compared in that order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity(pub u32, pub u32);
+
+impl CompoundSelector {
+    fn parse(text: &str) -> Option<Self> {
+        let mut compound = CompoundSelector::default();
+        let mut is_id = false;
+        let mut name = String::new();
+        // The trailing '.' flushes the last name.
+        for c in text.chars().chain(std::iter::once('.')) {
+            if c == '.' || c == '#' {
+                if !name.is_empty() {
+                    if is_id {
+                        if compound.id.is_some() {
+                            return None;
+                        }
+                        compound.id = Some(std::mem::take(&mut name));
+                    } else {
+                        compound.classes.push(std::mem::take(&mut name));
+                    }
+                } else if is_id {
+                    return None;
+                }
+                is_id = c == '#';
+            } else {
+                name.push(c);
+            }
+        }
+        if compound.id.is_none() && compound.classes.is_empty() {
+            return None;
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &ThemeElement) -> bool {
+        self.id.as_ref().is_none_or(|id| element.id == id.as_str())
+            && self.classes.iter().all(|class| element.has_class(class))
+    }
+}
+
+impl Selector {
+    pub fn parse(text: &str) -> Option<Self> {
+        let spaced = text.replace('>', " > ");
+        let mut parts = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        for token in spaced.split_whitespace() {
+            if token == ">" {
+                if parts.is_empty() || combinator == Combinator::Child {
+                    return None;
+                }
+                combinator = Combinator::Child;
+                continue;
+            }
+            parts.push((combinator, CompoundSelector::parse(token)?));
+            combinator = Combinator::Descendant;
+        }
+        if parts.is_empty() || combinator == Combinator::Child {
+            return None;
+        }
+        Some(Self { parts })
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        self.parts.iter().fold(Specificity::default(), |Specificity(ids, classes), (_, compound)| {
+            Specificity(ids + compound.id.is_some() as u32, classes + compound.classes.len() as u32)
+        })
+    }
+
+    // ancestors are the ThemeElements above element from its parent up, None for entities without one.
+    pub fn matches(&self, element: &ThemeElement, ancestors: &[Option<&ThemeElement>]) -> bool {
+        self.matches_part(self.parts.len() - 1, element, ancestors)
+    }
+
+    fn matches_part(&self, index: usize, element: &ThemeElement, ancestors: &[Option<&ThemeElement>]) -> bool {
+        let (combinator, compound) = &self.parts[index];
+        if !compound.matches(element) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        match combinator {
+            Combinator::Child => ancestors
+                .first()
+                .copied()
+                .flatten()
+                .is_some_and(|parent| self.matches_part(index - 1, parent, &ancestors[1..])),
+            Combinator::Descendant => (0..ancestors.len()).any(|i| {
+                ancestors[i].is_some_and(|ancestor| self.matches_part(index - 1, ancestor, &ancestors[i + 1..]))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(class: &'static str, id: &'static str) -> ThemeElement {
+        ThemeElement { class: class.into(), id: id.into() }
+    }
+
+    #[test]
+    fn parse_rejects_malformed_selectors() {
+        for text in ["", "   ", ">", "> a", "a >", "a > > b", "a >> b", "#", ".", "a#", "#a#b", "a#b#c"] {
+            assert_eq!(Selector::parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parse_accepts_combinators_with_or_without_spaces() {
+        assert_eq!(Selector::parse("a>b"), Selector::parse("a > b"));
+        assert_eq!(Selector::parse("  a   b "), Selector::parse("a b"));
+        assert_eq!(Selector::parse(".button"), Selector::parse("button"));
+        assert_ne!(Selector::parse("a > b"), Selector::parse("a b"));
+    }
+
+    #[test]
+    fn specificity_counts_ids_before_classes() {
+        let specificity = |text| Selector::parse(text).unwrap().specificity();
+        assert_eq!(specificity("button"), Specificity(0, 1));
+        assert_eq!(specificity("button.primary"), Specificity(0, 2));
+        assert_eq!(specificity("#save"), Specificity(1, 0));
+        assert_eq!(specificity("#d .primary#save"), Specificity(2, 1));
+        assert_eq!(specificity("dialog > button"), Specificity(0, 2));
+        assert!(specificity("#save") > specificity("a b c d.e"));
+    }
+
+    #[test]
+    fn matches_child_and_descendant() {
+        let dialog = element("dialog", "");
+        let row = element("row", "");
+        let button = element("button primary", "save");
+
+        let child = Selector::parse("dialog > button").unwrap();
+        assert!(child.matches(&button, &[Some(&dialog)]));
+        assert!(!child.matches(&button, &[Some(&row), Some(&dialog)]));
+        assert!(!child.matches(&button, &[None, Some(&dialog)]));
+
+        let descendant = Selector::parse("dialog button.primary#save").unwrap();
+        assert!(descendant.matches(&button, &[Some(&row), None, Some(&dialog)]));
+        assert!(!descendant.matches(&button, &[Some(&row)]));
+        assert!(!Selector::parse("button.secondary").unwrap().matches(&button, &[]));
+    }
+}
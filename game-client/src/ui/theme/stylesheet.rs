@@ -0,0 +1,335 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use bevy::asset::io::Reader;
+use bevy::asset::{ron, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ui::interaction::interaction_style::{MainStyle, NodeStyle, TextNodeLayout};
+use crate::ui::theme::Theme;
+
+// Class styles loaded from a *.theme.ron asset. Point a ThemeSource at one to fill the Theme next to it:
+// the resource for the global theme, the component for a subtree. Styles are applied again whenever the
+// file changes on disk, build with the hot_reload feature to have bevy watch the asset folder. Classes
+// removed from the file are removed from the theme too, or go back to the style they covered. assets/themes/default.theme.ron is the source of
+// the default theme.
+//
+// (
+//     classes: {
+//         "button": (
+//             default: (background_color: "#0000ff", border_color: "#ffffff", border_radius: Px(10.0)),
+//             hover: (background_color: "#ffff00", text_color: "#000000"),
+//         ),
+//         "text_input": (
+//             default: (background_color: "#cccccc", text_color: "#000000",
+//                 text_margin: (left: Px(5.0), right: Px(5.0), top: Px(5.0), bottom: Px(5.0))),
+//             focus: (background_color: "#e6e6e6", border_color: "#000000"),
+//         ),
+//         "dialog button.primary": (default: (background_color: "#2e7d32")),
+//     },
+// )
+//
+// Keys of classes are selectors, e.g. "dialog > button" or "#save", see Selector. Colors are hex strings.
+// A state style only lists what differs from the default style of its class.
+pub struct ThemeStylesheetPlugin;
+
+impl Plugin for ThemeStylesheetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ThemeStylesheet>();
+        app.register_asset_loader(ThemeStylesheetLoader);
+    }
+}
+
+#[derive(Asset, TypePath, Clone, Default)]
+pub struct ThemeStylesheet {
+    pub class_stylesheet: HashMap<Cow<'static, str>, NodeStyle>,
+}
+
+impl ThemeStylesheet {
+    // Classes in the stylesheet replace the ones in the theme, other classes keep theirs. replaced_styles holds
+    // what the theme had before an earlier version of this stylesheet applied a class, None when it had nothing.
+    // A class the stylesheet lost goes back to that style, so the built-in styles it covered come back.
+    pub fn apply_to(&self, theme: &mut Theme, replaced_styles: &mut HashMap<Cow<'static, str>, Option<NodeStyle>>) {
+        replaced_styles.retain(|class, replaced| {
+            if self.class_stylesheet.contains_key(class) {
+                return true;
+            }
+            match replaced.take() {
+                Some(style) => theme.class_stylesheet.insert(class.clone(), style),
+                None => theme.class_stylesheet.remove(class),
+            };
+            false
+        });
+        for (class, style) in self.class_stylesheet.iter() {
+            let replaced = theme.class_stylesheet.insert(class.clone(), style.clone());
+            replaced_styles.entry(class.clone()).or_insert(replaced);
+        }
+    }
+}
+
+// The stylesheet filling the Theme on the same entity, or the Theme resource when used as a resource.
+#[derive(Resource, Component, Clone)]
+pub struct ThemeSource {
+    pub handle: Handle<ThemeStylesheet>,
+    // The classes the stylesheet put into the theme, with the style each one replaced.
+    replaced_styles: HashMap<Cow<'static, str>, Option<NodeStyle>>,
+}
+
+impl ThemeSource {
+    pub fn new(handle: Handle<ThemeStylesheet>) -> Self {
+        Self { handle, replaced_styles: HashMap::new() }
+    }
+
+    fn apply(&mut self, stylesheet: &ThemeStylesheet, theme: &mut Theme) {
+        stylesheet.apply_to(theme, &mut self.replaced_styles);
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ThemeStylesheetFile {
+    #[serde(default)]
+    pub classes: HashMap<String, ClassStyleRules>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClassStyleRules {
+    #[serde(default)]
+    pub default: StyleRule,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hover: Option<StyleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressed: Option<StyleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<StyleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus: Option<StyleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hover_focus: Option<StyleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressed_focus: Option<StyleRule>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StyleRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_radius: Option<Val>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline: Option<OutlineRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_margin: Option<UiRect>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutlineRule {
+    pub width: Val,
+    #[serde(default)]
+    pub offset: Val,
+    pub color: String,
+}
+
+fn parse_color(hex: &str) -> Result<Color> {
+    Srgba::hex(hex)
+        .map(Color::from)
+        .map_err(|e| anyhow!("invalid color {:?}: {}", hex, e))
+}
+
+impl StyleRule {
+    // Fields of self win, the rest come from base.
+    fn merged_over(&self, base: &StyleRule) -> StyleRule {
+        StyleRule {
+            background_color: self.background_color.clone().or_else(|| base.background_color.clone()),
+            border_color: self.border_color.clone().or_else(|| base.border_color.clone()),
+            text_color: self.text_color.clone().or_else(|| base.text_color.clone()),
+            border_radius: self.border_radius.or(base.border_radius),
+            outline: self.outline.clone().or_else(|| base.outline.clone()),
+            text_margin: self.text_margin.or(base.text_margin),
+        }
+    }
+
+    fn to_main_style(&self) -> Result<MainStyle> {
+        let mut style = MainStyle::default();
+        if let Some(color) = &self.background_color {
+            style.background_color = BackgroundColor(parse_color(color)?);
+        }
+        if let Some(color) = &self.border_color {
+            style.border_color = BorderColor(parse_color(color)?);
+        }
+        if let Some(color) = &self.text_color {
+            style.text_color = TextColor(parse_color(color)?);
+        }
+        if let Some(radius) = self.border_radius {
+            style.border_radius = BorderRadius::all(radius);
+        }
+        if let Some(outline) = &self.outline {
+            style.outline = Outline::new(outline.width, outline.offset, parse_color(&outline.color)?);
+        }
+        if let Some(margin) = self.text_margin {
+            let mut text_node_style = TextNodeLayout::default();
+            text_node_style.0.margin = margin;
+            style.text_node_style = text_node_style;
+        }
+        Ok(style)
+    }
+}
+
+impl ClassStyleRules {
+    pub fn to_node_style(&self) -> Result<NodeStyle> {
+        let state_style = |rule: &Option<StyleRule>| -> Result<Option<MainStyle>> {
+            rule.as_ref()
+                .map(|rule| rule.merged_over(&self.default).to_main_style())
+                .transpose()
+        };
+        Ok(NodeStyle {
+            default_style: self.default.to_main_style()?,
+            hover_style: state_style(&self.hover)?,
+            pressed_style: state_style(&self.pressed)?,
+            disabled_style: state_style(&self.disabled)?,
+            focus_style: state_style(&self.focus)?,
+            hover_focus_style: state_style(&self.hover_focus)?,
+            pressed_focus_style: state_style(&self.pressed_focus)?,
+        })
+    }
+}
+
+impl ThemeStylesheetFile {
+    // Options can be written without Some(..).
+    pub fn parse(text: &str) -> Result<Self> {
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        Ok(options.from_str(text)?)
+    }
+
+    pub fn to_stylesheet(&self) -> Result<ThemeStylesheet> {
+        let mut class_stylesheet = HashMap::with_capacity(self.classes.len());
+        for (class, rules) in self.classes.iter() {
+            let style = rules.to_node_style().map_err(|e| anyhow!("class {:?}: {}", class, e))?;
+            class_stylesheet.insert(Cow::Owned(class.clone()), style);
+        }
+        Ok(ThemeStylesheet { class_stylesheet })
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeStylesheetLoader;
+
+impl AssetLoader for ThemeStylesheetLoader {
+    type Asset = ThemeStylesheet;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<ThemeStylesheet> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ThemeStylesheetFile::parse(std::str::from_utf8(&bytes)?)?.to_stylesheet()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+// Fills themes from their sources when the source is set or its stylesheet (re)loads. Runs before
+// on_changed_theme_system, which then restyles the affected elements.
+pub fn theme_stylesheet_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<ThemeStylesheet>>,
+    stylesheets: Res<Assets<ThemeStylesheet>>,
+    default_source: Option<ResMut<ThemeSource>>,
+    mut default_theme: ResMut<Theme>,
+    mut sources: Query<(Entity, &mut ThemeSource, Option<&mut Theme>)>,
+) {
+    let mut reloaded = Vec::new();
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => reloaded.push(*id),
+            _ => {}
+        }
+    }
+
+    // Remembering the applied classes isn't a change of the source, that would apply it again next frame.
+    if let Some(mut source) = default_source {
+        if source.is_changed() || reloaded.contains(&source.handle.id()) {
+            if let Some(stylesheet) = stylesheets.get(&source.handle) {
+                debug!(id = ?source.handle.id(), "Applying stylesheet to the default theme");
+                source.bypass_change_detection().apply(stylesheet, &mut default_theme);
+            }
+        }
+    }
+
+    for (entity, mut source, theme) in sources.iter_mut() {
+        if !source.is_changed() && !reloaded.contains(&source.handle.id()) {
+            continue;
+        }
+        let Some(stylesheet) = stylesheets.get(&source.handle) else {
+            continue;
+        };
+        debug!(?entity, id = ?source.handle.id(), "Applying stylesheet to theme");
+        let source = source.bypass_change_detection();
+        match theme {
+            Some(mut theme) => source.apply(stylesheet, &mut theme),
+            None => {
+                let mut theme = Theme::default();
+                source.apply(stylesheet, &mut theme);
+                commands.entity(entity).insert(theme);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(color: Color) -> NodeStyle {
+        NodeStyle {
+            default_style: MainStyle { background_color: BackgroundColor(color), ..default() },
+            ..default()
+        }
+    }
+
+    fn stylesheet(classes: &[(&'static str, Color)]) -> ThemeStylesheet {
+        ThemeStylesheet {
+            class_stylesheet: classes.iter().map(|(class, color)| (Cow::Borrowed(*class), style(*color))).collect(),
+        }
+    }
+
+    fn color(theme: &Theme, class: &str) -> Option<Color> {
+        theme.class_stylesheet.get(class).map(|style| style.default_style.background_color.0)
+    }
+
+    #[test]
+    fn spawned_classes_survive_a_reload() {
+        let built_in = Color::srgb(0.0, 0.0, 1.0);
+        let loaded = Color::srgb(1.0, 0.0, 0.0);
+        let mut theme = Theme::default();
+        theme.class_stylesheet.insert("button".into(), style(built_in));
+        theme.class_stylesheet.insert("text_input".into(), style(built_in));
+        let mut source = ThemeSource::new(Handle::default());
+
+        source.apply(&stylesheet(&[("button", loaded), ("dialog", loaded)]), &mut theme);
+        assert_eq!(color(&theme, "button"), Some(loaded));
+        assert_eq!(color(&theme, "dialog"), Some(loaded));
+        assert_eq!(color(&theme, "text_input"), Some(built_in));
+
+        // The reloaded file drops both classes: the built-in button comes back, the loaded-only dialog goes.
+        source.apply(&stylesheet(&[]), &mut theme);
+        assert_eq!(color(&theme, "button"), Some(built_in));
+        assert_eq!(color(&theme, "dialog"), None);
+        assert_eq!(color(&theme, "text_input"), Some(built_in));
+
+        source.apply(&stylesheet(&[("button", loaded)]), &mut theme);
+        source.apply(&stylesheet(&[("button", loaded)]), &mut theme);
+        source.apply(&stylesheet(&[]), &mut theme);
+        assert_eq!(color(&theme, "button"), Some(built_in));
+    }
+}
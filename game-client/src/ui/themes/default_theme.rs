@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use crate::ui::components::ui_elements::containers::MarginContainer;
 use crate::ui::interaction::interaction_style::{NodeStyle, MainStyle, TextNodeLayout};
 use crate::ui::theme::Theme;
+use crate::ui::theme::stylesheet::ThemeSource;
 
 pub struct DefaultThemePlugin;
 
@@ -10,9 +11,20 @@ impl Plugin for DefaultThemePlugin {
     fn build(&self, app: &mut App) {
         let mut theme = app.world_mut().resource_mut::<Theme>();
         initialize_default_theme(&mut theme);
+        app.add_systems(Startup, load_default_theme_source_system);
     }
 }
 
+pub const DEFAULT_THEME_PATH: &str = "themes/default.theme.ron";
+
+// The built-in styles below apply right away, the stylesheet goes over them once it is loaded.
+fn load_default_theme_source_system(mut commands: Commands, asset_server: Option<Res<AssetServer>>) {
+    let Some(asset_server) = asset_server else {
+        return;
+    };
+    commands.insert_resource(ThemeSource::new(asset_server.load(DEFAULT_THEME_PATH)));
+}
+
 
 fn initialize_default_theme(theme: &mut Theme) {
     let button_style = NodeStyle {
@@ -2,12 +2,14 @@ use std::collections::HashMap;
 use bevy::ecs::component::ComponentId;
 use crate::scene_system::{GenericScene, GenericSceneCommandsExt, SpawnState};
 use crate::ui::components::FULL_SIZE_NODE;
-use crate::event_system::UnhandledEventTriggerExt;
-use crate::ui::input::focus::InputFocusPolicy;
+use crate::event_system::tracer::{EventTracer, TraceEventAppExt};
+use crate::ui::input::focus::{AutoFocus, FocusPath, InputFocus, InputFocusPolicy, ManagedSceneFocus, QueueNextFocusTrait};
 use crate::ui::input::input_map::MappedInputEvent;
-use bevy::ecs::system::{RunSystemOnce};
+use bevy::ecs::entity::Entities;
+use bevy::ecs::system::{RunSystemOnce, SystemParam};
 use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
 
 // Plugin definition
 pub struct PageNavigationPlugin;
@@ -17,6 +19,7 @@ impl Plugin for PageNavigationPlugin {
         // Types
         app.register_type::<UiNavigation>();
         app.register_type::<UiNavigationEvent>();
+        app.register_type::<PageEntryFocus>();
 
         // Component hooks
         app.world_mut().register_component_hooks::<UiNavigation>()
@@ -25,6 +28,9 @@ impl Plugin for PageNavigationPlugin {
         // Observers
         app.add_observer(ui_navigation_button_observer);
         app.add_observer(ui_navigation_back_button_observer);
+        app.add_observer(ui_navigation_confirm_observer);
+        app.add_observer(ui_navigation_page_ready_observer);
+        app.trace_event::<UiNavigationEvent>();
 
         // Systems
         app.add_systems(PostUpdate, (ui_navigation_change_system,));
@@ -39,9 +45,30 @@ pub struct UiNavigation {
     pub root_page: Option<GenericScene>,
     pub path: Vec<String>,
     pub next_path: Option<Vec<String>>,
+    pub entry_focus: PageEntryFocus,
 
     #[reflect(ignore)]
     pub current_scene_root: Option<Entity>,
+
+    // Last focused element of each page, keyed by page name. The root page uses an empty key.
+    #[reflect(ignore)]
+    pub remembered_focus: HashMap<String, FocusPath>,
+}
+
+// Which element gets focus when a page of the navigation is shown.
+#[derive(Clone, Debug, Default, Reflect, PartialEq, Eq)]
+pub enum PageEntryFocus {
+    // The element that was focused when the page was left. First visits use AutoFocus.
+    #[default]
+    Remembered,
+    // Always the AutoFocus element of the page.
+    AutoFocus,
+    // The first element of the page that accepts focus.
+    FirstFocusable,
+    // The element with the given Name.
+    Named(String),
+    // Focus stays on the navigation.
+    None,
 }
 
 impl UiNavigation {
@@ -49,6 +76,10 @@ impl UiNavigation {
         self.path.last().cloned()
     }
 
+    pub fn get_current_page_key(&self) -> String {
+        self.get_current_path().unwrap_or_default()
+    }
+
     pub fn queue_pop_pages(&mut self, count: usize) {
         if let Some(next_path) = self.next_path.as_mut() {
             if !next_path.is_empty() {
@@ -155,16 +186,37 @@ fn ui_navigation_on_add(
     let mut observer = Observer::new(ui_navigation_event_observer);
     observer.watch_entity(entity);
     entity_commands.insert(observer);
-    entity_commands.insert(InputFocusPolicy::All);
+    entity_commands.insert((InputFocusPolicy::All, ManagedSceneFocus));
 }
 
 fn ui_navigation_change_system(
     mut commands: Commands,
     mut navs: Query<(Entity, Option<&Children>, &mut UiNavigation), Changed<UiNavigation>>,
+    input_focus: Res<InputFocus>,
+    parents: Query<&Parent>,
+    children_query: Query<&Children>,
+    names: Query<&Name>,
 ) {
     for (entity, children, mut nav) in navs.iter_mut() {
         debug!("handling page navigation");
         if let Some(next_path) = nav.next_path.take() {
+            if nav.path.last() != next_path.last() {
+                let page_key = nav.get_current_page_key();
+                let focus_path = input_focus.0.and_then(|focused| {
+                    page_focus_path(entity, focused, &parents, &children_query, &names)
+                });
+
+                match focus_path {
+                    Some(focus_path) => {
+                        debug!(?page_key, ?focus_path, "Remembering page focus");
+                        nav.remembered_focus.insert(page_key, focus_path);
+                    }
+                    None => {
+                        nav.remembered_focus.remove(&page_key);
+                    }
+                }
+            }
+
             let prev_page_path = nav.path.last();
 
             info!(
@@ -243,10 +295,94 @@ fn ui_navigation_change_system(
     }
 }
 
+// Path of the focused entity relative to the page root it belongs to. The page root is the direct child of
+// the navigation entity.
+fn page_focus_path(
+    nav_entity: Entity,
+    focused: Entity,
+    parents: &Query<&Parent>,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+) -> Option<FocusPath> {
+    let mut page_root = focused;
+    loop {
+        let parent = parents.get(page_root).ok()?.get();
+        if parent == nav_entity {
+            break;
+        }
+        page_root = parent;
+    }
+
+    FocusPath::from_entity(focused, page_root, parents, children, names)
+}
+
+// What PageEntryFocus looks for below a page root.
+#[derive(SystemParam)]
+struct EntryFocusCandidates<'w, 's> {
+    children: Query<'w, 's, &'static Children>,
+    names: Query<'w, 's, &'static Name>,
+    auto_focus: Query<'w, 's, (), With<AutoFocus>>,
+    focus_policies: Query<'w, 's, &'static InputFocusPolicy>,
+}
+
+// Once a page scene is spawned, give focus to the element selected by the navigation's PageEntryFocus.
+fn ui_navigation_page_ready_observer(
+    trigger: Trigger<SceneInstanceReady>,
+    navs: Query<&UiNavigation>,
+    parents: Query<&Parent>,
+    candidates: EntryFocusCandidates,
+    input_focus: Res<InputFocus>,
+    entities: &Entities,
+    mut commands: Commands,
+) {
+    let EntryFocusCandidates { children, names, auto_focus, focus_policies } = candidates;
+    let page_root = trigger.entity();
+    let Ok(nav_entity) = parents.get(page_root).map(|parent| parent.get()) else {
+        return;
+    };
+    let Ok(nav) = navs.get(nav_entity) else {
+        return;
+    };
+
+    // Only take over focus that fell back to the navigation or got lost with the previous page.
+    if let Some(focused) = input_focus.0 {
+        if focused != nav_entity && entities.contains(focused) {
+            return;
+        }
+    }
+
+    let page_key = nav.get_current_page_key();
+    let find_auto_focus = || {
+        children
+            .iter_descendants(page_root)
+            .find(|descendant| auto_focus.contains(*descendant))
+    };
+    let next_focus = match &nav.entry_focus {
+        PageEntryFocus::Remembered => nav
+            .remembered_focus
+            .get(&page_key)
+            .and_then(|focus_path| focus_path.resolve(page_root, &children, &names))
+            .or_else(find_auto_focus),
+        PageEntryFocus::AutoFocus => find_auto_focus(),
+        PageEntryFocus::FirstFocusable => children
+            .iter_descendants(page_root)
+            .find(|descendant| focus_policies.get(*descendant) == Ok(&InputFocusPolicy::All)),
+        PageEntryFocus::Named(name) => FocusPath::Name(name.clone()).resolve(page_root, &children, &names),
+        PageEntryFocus::None => None,
+    };
+
+    if let Some(next_focus) = next_focus {
+        debug!(?page_key, entity = ?next_focus, entry_focus = ?nav.entry_focus, "Page entry focus");
+        commands.queue_next_focus(Some(next_focus));
+    }
+}
+
 fn ui_navigation_event_observer(
     mut trigger: Trigger<UiNavigationEvent>,
+    mut tracer: EventTracer,
     mut navs: Query<&mut UiNavigation>,
 ) {
+    tracer.observer_ran(&trigger);
     if let Ok(mut ui_navigation) = navs.get_mut(trigger.observer()) {
         match trigger.event_mut() {
             UiNavigationEvent::AppendPath(new_page_stack) => {
@@ -280,9 +416,11 @@ fn ui_navigation_button_observer(
 
 fn ui_navigation_back_button_observer(
     mut trigger: Trigger<MappedInputEvent>,
+    mut tracer: EventTracer,
     mut commands: Commands,
     query: Query<(Entity, &UiNavigation)>,
 ) {
+    tracer.observer_ran(&trigger);
     if trigger.event().keys.contains(&"ui_focus_release".to_string()) {
         info!(entity = ?trigger.entity(), "Navigation back button detected");
         let entity = trigger.entity();
@@ -295,7 +433,25 @@ fn ui_navigation_back_button_observer(
             debug!(?entity, "Handling navigation back button");
             let navigation_event = UiNavigationEvent::PopPath(1);
             commands.trigger_targets(navigation_event.clone(), entity);
-            trigger.set_as_handled();
+            tracer.set_as_handled(&mut trigger);
         }
     }
-}
\ No newline at end of file
+}
+
+// Lets keyboard and gamepad users activate a focused navigation button.
+fn ui_navigation_confirm_observer(
+    mut trigger: Trigger<MappedInputEvent>,
+    mut tracer: EventTracer,
+    mut commands: Commands,
+    query: Query<&UiNavigationEvent>,
+) {
+    tracer.observer_ran(&trigger);
+    if trigger.event().keys.contains("ui_confirm") {
+        let entity = trigger.entity();
+        if let Ok(navigation_event) = query.get(entity) {
+            info!(?navigation_event, "Navigation button confirm detected");
+            commands.trigger_targets(navigation_event.clone(), entity);
+            tracer.set_as_handled(&mut trigger);
+        }
+    }
+}
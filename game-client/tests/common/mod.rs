@@ -0,0 +1,92 @@
+// Shared by the test crates, each of them uses a part of it.
+#![allow(dead_code)]
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::scene::ScenePlugin;
+use game_client::scene_system::{SceneMap, SceneSystemPlugin};
+use game_client::ui::components::text_creator::TextCreator;
+use game_client::ui::input::focus::InputFocus;
+use game_client::ui::plugins::UiPlugin;
+use game_client::ui::ui_navigation::UiNavigation;
+
+// The UI without a window or renderer. Nothing computes layout or visibility here, so
+// stack_nodes_system stands in for them.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        HierarchyPlugin,
+        TransformPlugin,
+        ScenePlugin,
+        bevy::input::InputPlugin,
+        WindowPlugin { primary_window: None, exit_condition: bevy::window::ExitCondition::DontExit, ..default() },
+        bevy::text::TextPlugin,
+        bevy::ui::UiPlugin { enable_rendering: false, add_picking: false },
+    ));
+    app.init_asset::<Image>();
+    app.init_asset::<TextureAtlasLayout>();
+    app.register_type::<Visibility>();
+    app.register_type::<InheritedVisibility>();
+    app.register_type::<ViewVisibility>();
+    app.add_plugins((SceneSystemPlugin, UiPlugin));
+    app.add_systems(PostUpdate, stack_nodes_system.after(TransformSystem::TransformPropagate));
+    app.add_systems(Startup, spawn_main_pages);
+    app
+}
+
+fn spawn_main_pages(mut commands: Commands, scene_map: Res<SceneMap>) {
+    scene_map.scenes.get("main_pages").unwrap().clone().spawn_with_commands(&mut commands);
+}
+
+// Every node is visible and one row below the node before it in hierarchy order, like a column.
+fn stack_nodes_system(
+    roots: Query<Entity, (With<Node>, Without<Parent>)>,
+    children: Query<&Children>,
+    mut nodes: Query<(&mut GlobalTransform, &mut InheritedVisibility), With<Node>>,
+) {
+    let mut row = 0.0;
+    for root in roots.iter() {
+        for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+            if let Ok((mut transform, mut visibility)) = nodes.get_mut(entity) {
+                *transform = GlobalTransform::from_translation(Vec3::new(0.0, row, 0.0));
+                *visibility = InheritedVisibility::VISIBLE;
+                row += 10.0;
+            }
+        }
+    }
+}
+
+pub fn navigation_path(app: &mut App) -> Vec<String> {
+    let mut navigations = app.world_mut().query::<&UiNavigation>();
+    navigations.single(app.world()).path.clone()
+}
+
+// The TextCreator text of the focused element.
+pub fn focused_text(app: &mut App) -> Option<String> {
+    let focused = app.world().resource::<InputFocus>().0?;
+    let text_creator = app.world().get::<TextCreator>(focused)?;
+    Some(text_creator.text.to_string())
+}
+
+pub fn update_frames(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+// Presses and releases the key, a few frames apart like a player would.
+pub fn tap_key(app: &mut App, key_code: KeyCode, logical_key: Key) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key: logical_key.clone(),
+            state,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+        update_frames(app, 3);
+    }
+}
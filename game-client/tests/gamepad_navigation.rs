@@ -0,0 +1,43 @@
+mod common;
+
+use std::time::Duration;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent, RawGamepadButtonChangedEvent, RawGamepadEvent};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+// Presses are sent as raw events like a gamepad backend would, bevy's gamepad processing turns them into
+// the GamepadButtonChangedEvent and GamepadButtonStateChangedEvent the input map reads.
+fn press(app: &mut App, gamepad: Entity, button: GamepadButton) {
+    for value in [1.0, 0.0] {
+        app.world_mut().send_event(RawGamepadEvent::Button(RawGamepadButtonChangedEvent::new(gamepad, button, value)));
+        common::update_frames(app, 3);
+    }
+}
+
+#[test]
+fn gamepad_moves_focus_and_pops_the_page() {
+    let mut app = common::headless_app();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1) / 60));
+
+    let gamepad = app.world_mut().spawn_empty().id();
+    app.world_mut().send_event(GamepadConnectionEvent::new(gamepad, GamepadConnection::Connected {
+        name: "Test gamepad".into(),
+        vendor_id: None,
+        product_id: None,
+    }));
+
+    // Past the loading page.
+    common::update_frames(&mut app, 90);
+    assert_eq!(common::navigation_path(&mut app), Vec::<String>::new());
+    assert_eq!(common::focused_text(&mut app).as_deref(), Some("New Game"));
+
+    press(&mut app, gamepad, GamepadButton::DPadDown);
+    assert_eq!(common::focused_text(&mut app).as_deref(), Some("Options"));
+
+    press(&mut app, gamepad, GamepadButton::South);
+    assert_eq!(common::navigation_path(&mut app), vec!["options".to_string()]);
+
+    press(&mut app, gamepad, GamepadButton::East);
+    assert_eq!(common::navigation_path(&mut app), Vec::<String>::new());
+    assert_eq!(common::focused_text(&mut app).as_deref(), Some("Options"));
+}
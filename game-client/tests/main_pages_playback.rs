@@ -0,0 +1,39 @@
+mod common;
+
+use std::path::Path;
+use game_client::ui::input::recording::{InputPlayback, InputRecording};
+
+// Replays tests/recordings/main_pages.ron: wait out the loading page, arrow down to Options, open it and
+// go back with Escape. Time only moves with the recording's frames, so every run sees the same states.
+#[test]
+fn main_pages_navigation_playback() {
+    let recording = InputRecording::load(Path::new("tests/recordings/main_pages.ron")).unwrap();
+    let mut app = common::headless_app();
+    app.insert_resource(InputPlayback::new(recording));
+
+    let mut states: Vec<(Vec<String>, Option<String>)> = Vec::new();
+    let mut settle_frames = 30;
+    while settle_frames > 0 {
+        app.update();
+        if app.world().resource::<InputPlayback>().is_finished() {
+            settle_frames -= 1;
+        }
+        let state = (common::navigation_path(&mut app), common::focused_text(&mut app));
+        if states.last() != Some(&state) {
+            states.push(state);
+        }
+    }
+
+    let path = |path: &[&str]| path.iter().map(|page| page.to_string()).collect::<Vec<_>>();
+    let focus = |text: &str| Some(text.to_string());
+    // A page gets its focus the frame after it is spawned. The missing options page has nothing to focus.
+    assert_eq!(states, vec![
+        (path(&["loading"]), None),
+        (path(&[]), None),
+        (path(&[]), focus("New Game")),
+        (path(&[]), focus("Options")),
+        (path(&["options"]), None),
+        (path(&[]), None),
+        (path(&[]), focus("Options")),
+    ]);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::time::Duration;
+use bevy::input::keyboard::Key;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use game_client::ui::input::focus::InputFocus;
+use game_client::ui::ui_navigation::UiNavigationEvent;
+
+#[derive(Resource, Default)]
+struct PagePops(usize);
+
+#[test]
+fn escape_in_a_text_field_releases_it_and_keeps_the_page() {
+    let mut app = common::headless_app();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1) / 60));
+    app.init_resource::<PagePops>();
+    app.add_observer(|trigger: Trigger<UiNavigationEvent>, mut pops: ResMut<PagePops>| {
+        if matches!(trigger.event(), UiNavigationEvent::PopPath(_)) {
+            pops.0 += 1;
+        }
+    });
+
+    // Past the loading page.
+    common::update_frames(&mut app, 90);
+    assert_eq!(common::focused_text(&mut app).as_deref(), Some("New Game"));
+
+    common::tap_key(&mut app, KeyCode::ArrowDown, Key::ArrowDown);
+    common::tap_key(&mut app, KeyCode::ArrowDown, Key::ArrowDown);
+    let text_field = app.world().resource::<InputFocus>().0.unwrap();
+    assert_eq!(common::focused_text(&mut app).as_deref(), Some("H "));
+
+    common::tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    let focus = app.world().resource::<InputFocus>().0;
+    assert_ne!(focus, Some(text_field));
+    assert_eq!(app.world().resource::<PagePops>().0, 0);
+    assert!(app.world().get_entity(text_field).is_ok());
+    assert_eq!(common::navigation_path(&mut app), Vec::<String>::new());
+
+    // Without the text field focused, Escape goes back to cancelling.
+    common::tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    assert_eq!(app.world().resource::<PagePops>().0, 1);
+}
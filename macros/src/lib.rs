@@ -0,0 +1,103 @@
+use proc_macro::TokenStream;
+use proc_macro_crate::{crate_name, FoundCrate};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
+
+// Declares an event that can be marked handled while it bubbles, e.g.
+//
+// #[derive(Component, Clone, HandledEvent)]
+// #[event(traversal = Parent, auto_propagate)]
+// pub struct MyEvent {
+//     pub(crate) is_handled: bool,
+// }
+//
+// Generates the Event impl (no traversal and no auto propagation unless given) and HandledEventExt on
+// the bool field marked #[handled], or the one named is_handled.
+#[proc_macro_derive(HandledEvent, attributes(event, handled))]
+pub fn derive_handled_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_handled_event(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_handled_event(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut traversal = None;
+    let mut auto_propagate = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("event")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("traversal") {
+                traversal = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else if meta.path.is_ident("auto_propagate") {
+                auto_propagate = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `traversal = <component>` or `auto_propagate`"))
+            }
+        })?;
+    }
+
+    // Traversals are read-only queries, `traversal = Parent` means `&'static Parent`.
+    let traversal = match traversal {
+        None => quote!(()),
+        Some(Type::Tuple(tuple)) if tuple.elems.is_empty() => quote!(()),
+        Some(Type::Reference(reference)) => quote!(#reference),
+        Some(component) => quote!(&'static #component),
+    };
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "HandledEvent needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "HandledEvent can only be derived for structs")),
+    };
+
+    let handled_field = fields
+        .iter()
+        .find(|field| field.attrs.iter().any(|attr| attr.path().is_ident("handled")))
+        .or_else(|| fields.iter().find(|field| field.ident.as_ref().is_some_and(|ident| ident == "is_handled")))
+        .and_then(|field| field.ident.as_ref())
+        .ok_or_else(|| syn::Error::new_spanned(
+            &input.ident,
+            "HandledEvent needs a bool field marked #[handled] or named `is_handled`",
+        ))?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let game_client = game_client_path()?;
+
+    Ok(quote! {
+        impl #impl_generics #game_client::event_system::__macro_support::Event for #name #type_generics #where_clause {
+            type Traversal = #traversal;
+            const AUTO_PROPAGATE: bool = #auto_propagate;
+        }
+
+        impl #impl_generics #game_client::event_system::HandledEventExt for #name #type_generics #where_clause {
+            fn is_handled(&self) -> bool {
+                self.#handled_field
+            }
+
+            fn set_handled(&mut self, handled: bool, _: #game_client::event_system::HandledToken) {
+                self.#handled_field = handled;
+            }
+        }
+    })
+}
+
+// The game-client crate as the deriving crate knows it. Inside game-client, its binaries and tests it is
+// game_client (the library names itself that way with `extern crate self as game_client`), elsewhere it
+// may be renamed in Cargo.toml. Bevy paths go through its event_system::__macro_support re-exports.
+fn game_client_path() -> syn::Result<TokenStream2> {
+    match crate_name("game-client") {
+        Ok(FoundCrate::Itself) => Ok(quote!(::game_client)),
+        Ok(FoundCrate::Name(name)) => {
+            let ident = Ident::new(&name, Span::call_site());
+            Ok(quote!(::#ident))
+        }
+        Err(e) => Err(syn::Error::new(Span::call_site(), format!("HandledEvent needs the game-client crate: {}", e))),
+    }
+}